@@ -0,0 +1,80 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+
+use crabpicker::framebuffer::{Framebuffer, LensStyle};
+
+const RESOLUTIONS: [(&str, u32, u32); 3] = [
+    ("1080p", 1920, 1080),
+    ("4k", 3840, 2160),
+    ("8k", 7680, 4320),
+];
+
+fn gradient(width: u32, height: u32) -> Vec<u32> {
+    (0..width * height)
+        .map(|index| (index % width) << 16 | (index / width) << 8 | (index % 251))
+        .collect()
+}
+
+/// Drawing the lens should cost the same no matter how big the monitor is
+fn draw_lens(c: &mut Criterion) {
+    let mut group = c.benchmark_group("draw_lens");
+
+    for (name, width, height) in RESOLUTIONS {
+        let mut framebuffer = Framebuffer::new(width, height, gradient(width, height));
+        let mut x = 0;
+
+        group.bench_function(BenchmarkId::from_parameter(name), |b| {
+            b.iter(|| {
+                x = (x + 7) % width;
+                framebuffer.clear();
+                framebuffer.draw_lens(black_box((x, height / 2)), 16, 11, &LensStyle::default());
+            })
+        });
+    }
+
+    group.finish();
+}
+
+fn clear(c: &mut Criterion) {
+    let mut group = c.benchmark_group("clear");
+
+    for (name, width, height) in RESOLUTIONS {
+        let mut framebuffer = Framebuffer::new(width, height, gradient(width, height));
+
+        group.bench_function(BenchmarkId::from_parameter(name), |b| {
+            b.iter(|| {
+                framebuffer.draw_lens((width / 2, height / 2), 16, 11, &LensStyle::default());
+                framebuffer.clear();
+            })
+        });
+    }
+
+    group.finish();
+}
+
+/// A whole redraw like `draw_window` does, presenting into a buffer that holds the last frame.
+///
+/// Only the old and new lens are copied, so with the same lens 1080p and 4k should take as long
+fn redraw(c: &mut Criterion) {
+    let mut group = c.benchmark_group("redraw");
+
+    for (name, width, height) in RESOLUTIONS.into_iter().take(2) {
+        let mut framebuffer = Framebuffer::new(width, height, gradient(width, height));
+        let mut presented = vec![0; framebuffer.pixels().len()];
+        framebuffer.present_into(&mut presented, 0);
+        let mut x = 0;
+
+        group.bench_function(BenchmarkId::from_parameter(name), |b| {
+            b.iter(|| {
+                x = (x + 7) % width;
+                framebuffer.clear();
+                framebuffer.draw_lens(black_box((x, height / 2)), 16, 11, &LensStyle::default());
+                black_box(framebuffer.present_into(&mut presented, 1));
+            })
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, draw_lens, clear, redraw);
+criterion_main!(benches);
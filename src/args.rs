@@ -1,4 +1,4 @@
-use std::fmt::Display;
+use std::{fmt::Display, path::PathBuf};
 
 use clap::{Parser, ValueEnum};
 
@@ -8,9 +8,21 @@ use clap::{Parser, ValueEnum};
     version,
     about,
     long_about = "A GUI color picker with a tooglable zoom via CTRL or on hold with Z
+Tapping CTRL toggles the zoom once it is released, so holding it to move faster or with other keys leaves the zoom alone
 You can either use left click or ENTER to ouput the color to your CLI and your clipboard
 When zooming you can use the scroll wheel to change the zoom scale and with shift to change the zoom size
-You can move around for precise measurement with wasd, vim or arrow key movement"
+You can move around for precise measurement with wasd, vim or arrow key movement, hold shift or CTRL to move 10 or 100 pixels at a time
+Press V to cycle the zoom through showing single channels, luma, saturation and the difference from the center pixel
+Press 1, 2, 3 or 4 to see the screen with protanopia, deuteranopia, tritanopia or achromatopsia, the picked color is then output as both
+Press M to set an anchor and measure the distance to it, which is output instead of the color, press N to toggle snapping its ends to edges
+Hold alt while moving to jump to the next pixel with a different color
+Press T to estimate the text and background colors of anti-aliased text around the cursor instead
+Press G and then drag or click twice to sample a gradient along a line and output it as CSS
+Press F to flood fill the area of a similar color around a click, click inside of it to output its size and average color
+Press C to dim everything that does not match the hovered color on every monitor
+Press P to pin the color and lens under the cursor to compare it against, backspace removes the last pin
+Press E to export a screenshot with the pins marked on it and a table of them
+Press I to save the capture of the monitor or the area measured with M to a PNG, with shift to save the lens instead"
 )]
 pub struct Args {
     /// Turns on zoom at the start
@@ -25,6 +37,19 @@ pub struct Args {
     #[arg(long, default_value_t = 11, value_parser = valid_zoom_size)]
     pub size: u32,
 
+    /// The size of the square around the cursor a pick combines, like 1x1, 3x3 or 5x5
+    #[arg(long, default_value = "1x1", value_parser = valid_sample_size)]
+    pub sample: u32,
+
+    /// How many pixels around the cursor a pick combines on every side, 1 samples 3x3. Replaces
+    /// `--sample`
+    #[arg(long, value_name = "RADIUS", conflicts_with = "sample")]
+    pub sample_radius: Option<u32>,
+
+    /// How the pixels of a bigger sample are combined
+    #[arg(long, default_value_t = SampleMode::Mean)]
+    pub sample_mode: SampleMode,
+
     /// The color format that will be printed and put in your clipboard
     #[arg(short, long, default_value_t = ColorFormat::Hex)]
     pub format: ColorFormat,
@@ -33,9 +58,80 @@ pub struct Args {
     #[arg(long, default_value_t = false)]
     pub disable_clipboard: bool,
 
+    /// Picks a foreground and then a background color and prints their WCAG and APCA contrast
+    #[arg(long, default_value_t = false)]
+    pub contrast: bool,
+
+    /// Outputs every color along a gradient instead of a CSS linear-gradient
+    #[arg(long, default_value_t = false)]
+    pub gradient_list: bool,
+
+    /// Disables the label with the color and coordinates under the zoom and the values written
+    /// into its pixels when zoomed in far enough
+    #[arg(long, default_value_t = false)]
+    pub disable_hud: bool,
+
+    /// Draws a round zoom instead of a square one
+    #[arg(long, default_value_t = false)]
+    pub circle: bool,
+
+    /// Disables the grid between the pixels of the zoom
+    #[arg(long, default_value_t = false)]
+    pub disable_grid: bool,
+
+    /// Color of the grid as RRGGBB, by default it contrasts with what is being zoomed in on
+    #[arg(long, value_parser = parse_hex_color)]
+    pub grid_color: Option<u32>,
+
+    /// Draws a shadow under the zoom
+    #[arg(long, default_value_t = false)]
+    pub shadow: bool,
+
+    /// Moves the zoom away from the cursor by X,Y pixels so it does not cover what you are picking
+    #[arg(long, default_value = "0,0", value_parser = parse_offset, allow_hyphen_values = true)]
+    pub offset: (i32, i32),
+
+    /// How much any channel has to change for alt movement and snapping to see an edge
+    #[arg(long, default_value_t = 32)]
+    pub edge_threshold: u8,
+
+    /// How much any channel may differ from the clicked pixel for the magic wand to fill it
+    #[arg(long, default_value_t = 16)]
+    pub wand_tolerance: u8,
+
+    /// How much any channel may differ from the hovered color for C to highlight a pixel
+    #[arg(long, default_value_t = 8)]
+    pub match_tolerance: u8,
+
+    /// Where E exports the pins marked on a screenshot, with a table of them next to it
+    #[arg(long, default_value = "spec-sheet.png")]
+    pub spec_sheet: PathBuf,
+
+    /// What the table of the exported pins is written as
+    #[arg(long, default_value_t = TableFormat::Markdown)]
+    pub spec_table: TableFormat,
+
+    /// Crops the exported screenshot to around the pins
+    #[arg(long, default_value_t = false)]
+    pub spec_crop: bool,
+
+    /// Writes the capture of the monitor under the cursor, or the area measured with M, to FILE
+    /// when picking. I saves it to FILE or capture.png at any time
+    #[arg(long, value_name = "FILE")]
+    pub save_capture: Option<PathBuf>,
+
+    /// Writes the lens as it is shown to FILE when picking. Shift and I saves it to FILE or
+    /// lens.png at any time
+    #[arg(long, value_name = "FILE")]
+    pub save_lens: Option<PathBuf>,
+
     /// This potentially helps with with fullscreening issues
     #[arg(long, default_value_t = false)]
     pub exclusive: bool,
+
+    /// Prints how long each stage of starting up took
+    #[arg(long, default_value_t = false)]
+    pub timings: bool,
 }
 
 fn valid_zoom_size(s: &str) -> Result<u32, String> {
@@ -46,6 +142,37 @@ fn valid_zoom_size(s: &str) -> Result<u32, String> {
     }
 }
 
+fn valid_sample_size(s: &str) -> Result<u32, String> {
+    let size = match s.split_once('x') {
+        Some((width, height)) if width == height => width,
+        Some(_) => return Err(format!("{s} is not a square!")),
+        None => s,
+    };
+
+    valid_zoom_size(size)
+}
+
+fn parse_hex_color(s: &str) -> Result<u32, String> {
+    let hex = s.strip_prefix('#').unwrap_or(s);
+
+    // `from_str_radix` would also take a sign in front of the digits
+    if hex.len() != 6 || !hex.bytes().all(|digit| digit.is_ascii_hexdigit()) {
+        return Err(format!("{s} is not in the form RRGGBB!"));
+    }
+
+    u32::from_str_radix(hex, 16).map_err(|err| err.to_string())
+}
+
+fn parse_offset(s: &str) -> Result<(i32, i32), String> {
+    let Some((x, y)) = s.split_once(',') else {
+        return Err(format!("{s} is not in the form X,Y!"));
+    };
+
+    let parse = |n: &str| n.trim().parse::<i32>().map_err(|err| err.to_string());
+
+    Ok((parse(x)?, parse(y)?))
+}
+
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Debug)]
 pub enum ColorFormat {
     /// Saves color in hex code: #RRGGBB
@@ -54,6 +181,59 @@ pub enum ColorFormat {
     Rgb,
 }
 
+impl ColorFormat {
+    pub fn format(self, (r, g, b): (u8, u8, u8)) -> String {
+        match self {
+            Self::Hex => format!("#{r:02X}{g:02X}{b:02X}"),
+            Self::Rgb => format!("{r}, {g}, {b}"),
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Debug)]
+pub enum SampleMode {
+    /// The average of every channel
+    Mean,
+    /// The middle value of every channel, which ignores outliers like noise
+    Median,
+    /// The average of the light of every channel, which is how the pixels blend to the eye
+    GammaMean,
+}
+
+impl Display for SampleMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Mean => write!(f, "mean"),
+            Self::Median => write!(f, "median"),
+            Self::GammaMean => write!(f, "gamma-mean"),
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Debug)]
+pub enum TableFormat {
+    Markdown,
+    Json,
+}
+
+impl TableFormat {
+    pub const fn extension(self) -> &'static str {
+        match self {
+            Self::Markdown => "md",
+            Self::Json => "json",
+        }
+    }
+}
+
+impl Display for TableFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Markdown => write!(f, "markdown"),
+            Self::Json => write!(f, "json"),
+        }
+    }
+}
+
 impl Display for ColorFormat {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -0,0 +1,168 @@
+use std::fmt::Display;
+
+use crate::srgb;
+
+/// A WCAG 2.1 contrast level, with the ratio normal and large text need to pass it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    AA,
+    AAA,
+}
+
+impl Level {
+    pub const fn normal_text(self) -> f64 {
+        match self {
+            Self::AA => 4.5,
+            Self::AAA => 7.0,
+        }
+    }
+
+    pub const fn large_text(self) -> f64 {
+        match self {
+            Self::AA => 3.0,
+            Self::AAA => 4.5,
+        }
+    }
+}
+
+impl Display for Level {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::AA => write!(f, "AA"),
+            Self::AAA => write!(f, "AAA"),
+        }
+    }
+}
+
+/// Relative luminance as WCAG 2.1 defines it
+pub fn relative_luminance((r, g, b): (u8, u8, u8)) -> f64 {
+    let linear = |channel: u8| srgb::to_linear(channel as f32 / 255.0) as f64;
+
+    0.0722f64.mul_add(linear(b), 0.2126f64.mul_add(linear(r), 0.7152 * linear(g)))
+}
+
+/// The WCAG 2.1 contrast ratio, from 1 to 21 no matter which color is lighter
+pub fn contrast_ratio(foreground: (u8, u8, u8), background: (u8, u8, u8)) -> f64 {
+    let foreground = relative_luminance(foreground);
+    let background = relative_luminance(background);
+
+    (foreground.max(background) + 0.05) / (foreground.min(background) + 0.05)
+}
+
+/// The APCA (0.0.98G-4g) lightness contrast of text on a background, negative for light text on
+/// a dark background
+pub fn apca_contrast(text: (u8, u8, u8), background: (u8, u8, u8)) -> f64 {
+    let luminance = |(r, g, b): (u8, u8, u8)| {
+        let linear = |channel: u8| (channel as f64 / 255.0).powf(2.4);
+        let y = 0.0721750f64.mul_add(
+            linear(b),
+            0.2126729f64.mul_add(linear(r), 0.7151522 * linear(g)),
+        );
+
+        // Soft clamp near black
+        if y < 0.022 {
+            y + (0.022 - y).powf(1.414)
+        } else {
+            y
+        }
+    };
+
+    let text = luminance(text);
+    let background = luminance(background);
+
+    if (background - text).abs() < 0.0005 {
+        return 0.0;
+    }
+
+    let lc = if background > text {
+        let contrast = (background.powf(0.56) - text.powf(0.57)) * 1.14;
+
+        if contrast < 0.1 {
+            0.0
+        } else {
+            contrast - 0.027
+        }
+    } else {
+        let contrast = (background.powf(0.65) - text.powf(0.62)) * 1.14;
+
+        if contrast > -0.1 {
+            0.0
+        } else {
+            contrast + 0.027
+        }
+    };
+
+    lc * 100.0
+}
+
+/// The closest foreground to the given one that passes `level` for normal text, found by mixing
+/// it with black or white as little as possible.
+///
+/// Returns `None` if even black and white do not pass.
+pub fn nearest_passing(
+    foreground: (u8, u8, u8),
+    background: (u8, u8, u8),
+    level: Level,
+) -> Option<(u8, u8, u8)> {
+    let mix = |(r, g, b): (u8, u8, u8), target: u8, amount: u32| {
+        let channel =
+            |channel: u8| ((channel as u32 * (255 - amount) + target as u32 * amount) / 255) as u8;
+
+        (channel(r), channel(g), channel(b))
+    };
+
+    let passes = |color| contrast_ratio(color, background) >= level.normal_text();
+
+    (0..=255)
+        .flat_map(|amount| [mix(foreground, 0, amount), mix(foreground, 255, amount)])
+        .find(|&color| passes(color))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BLACK: (u8, u8, u8) = (0, 0, 0);
+    const WHITE: (u8, u8, u8) = (255, 255, 255);
+
+    #[test]
+    fn contrast_ratio_spans_1_to_21() {
+        assert!((contrast_ratio(BLACK, WHITE) - 21.0).abs() < 1e-3);
+        assert!((contrast_ratio(WHITE, BLACK) - 21.0).abs() < 1e-3);
+        assert!((contrast_ratio((119, 119, 119), (119, 119, 119)) - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn contrast_ratio_matches_wcag_examples() {
+        // #767676 is the lightest gray that passes AA on white
+        assert!(contrast_ratio((0x76, 0x76, 0x76), WHITE) >= Level::AA.normal_text());
+        assert!(contrast_ratio((0x77, 0x77, 0x77), WHITE) < Level::AA.normal_text());
+    }
+
+    /// Values of the APCA reference implementation, 0.0.98G-4g
+    #[test]
+    fn apca_matches_reference() {
+        let cases = [
+            (BLACK, WHITE, 106.04),
+            (WHITE, BLACK, -107.88),
+            ((0x88, 0x88, 0x88), WHITE, 63.06),
+            (WHITE, (0x88, 0x88, 0x88), -68.54),
+            (BLACK, (0xAA, 0xAA, 0xAA), 58.15),
+            ((0xAA, 0xAA, 0xAA), BLACK, -56.24),
+        ];
+
+        for (text, background, expected) in cases {
+            let lc = apca_contrast(text, background);
+
+            assert!(
+                (lc - expected).abs() < 0.01,
+                "{text:?} on {background:?} was Lc {lc} instead of {expected}"
+            );
+        }
+    }
+
+    #[test]
+    fn apca_is_zero_for_the_same_color() {
+        assert_eq!(apca_contrast((0x88, 0x88, 0x88), (0x88, 0x88, 0x88)), 0.0);
+    }
+}
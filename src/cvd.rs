@@ -0,0 +1,159 @@
+use std::{fmt::Display, sync::OnceLock};
+
+use crate::framebuffer::{from_rgb, to_rgb};
+use crate::srgb;
+
+/// A color vision deficiency the screen can be shown as
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Deficiency {
+    Protanopia,
+    Deuteranopia,
+    Tritanopia,
+    Achromatopsia,
+}
+
+/// Machado, Oliveira and Fernandes (2009) at full severity, for linear RGB
+const PROTANOPIA: [[f32; 3]; 3] = [
+    [0.152286, 1.052583, -0.204868],
+    [0.114503, 0.786281, 0.099216],
+    [-0.003882, -0.048116, 1.051998],
+];
+
+const DEUTERANOPIA: [[f32; 3]; 3] = [
+    [0.367322, 0.860646, -0.227968],
+    [0.280085, 0.672501, 0.047413],
+    [-0.011820, 0.042940, 0.968881],
+];
+
+const TRITANOPIA: [[f32; 3]; 3] = [
+    [1.255528, -0.076749, -0.178779],
+    [-0.078411, 0.930809, 0.147602],
+    [0.004733, 0.691367, 0.303900],
+];
+
+/// Only the luminance is left without any working cones
+const ACHROMATOPSIA: [[f32; 3]; 3] = [[0.2126, 0.7152, 0.0722]; 3];
+
+/// Steps of the table used to go back from linear to sRGB
+const ENCODE_STEPS: usize = 4096;
+
+impl Deficiency {
+    const fn matrix(self) -> &'static [[f32; 3]; 3] {
+        match self {
+            Self::Protanopia => &PROTANOPIA,
+            Self::Deuteranopia => &DEUTERANOPIA,
+            Self::Tritanopia => &TRITANOPIA,
+            Self::Achromatopsia => &ACHROMATOPSIA,
+        }
+    }
+
+    /// How a 0RGB pixel looks to someone with this deficiency
+    pub fn simulate(self, pixel: u32) -> u32 {
+        let (decode, encode) = tables();
+        let (r, g, b) = to_rgb(pixel);
+        let linear = [decode[r as usize], decode[g as usize], decode[b as usize]];
+
+        let channel = |row: &[f32; 3]| {
+            let value = row[2].mul_add(linear[2], row[1].mul_add(linear[1], row[0] * linear[0]));
+            encode[(value.clamp(0.0, 1.0) * (ENCODE_STEPS - 1) as f32).round() as usize]
+        };
+
+        let [red, green, blue] = self.matrix();
+
+        from_rgb((channel(red), channel(green), channel(blue)))
+    }
+}
+
+impl Display for Deficiency {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Protanopia => write!(f, "protanopia"),
+            Self::Deuteranopia => write!(f, "deuteranopia"),
+            Self::Tritanopia => write!(f, "tritanopia"),
+            Self::Achromatopsia => write!(f, "achromatopsia"),
+        }
+    }
+}
+
+/// Lookup tables from sRGB to linear and back, a whole monitor is too many pixels for `powf`
+fn tables() -> &'static ([f32; 256], Vec<u8>) {
+    static TABLES: OnceLock<([f32; 256], Vec<u8>)> = OnceLock::new();
+
+    TABLES.get_or_init(|| {
+        let mut decode = [0.0; 256];
+        for (value, linear) in decode.iter_mut().enumerate() {
+            *linear = srgb::to_linear(value as f32 / 255.0);
+        }
+
+        let encode = (0..ENCODE_STEPS)
+            .map(|step| {
+                let srgb = srgb::from_linear(step as f32 / (ENCODE_STEPS - 1) as f32);
+                (srgb * 255.0).round() as u8
+            })
+            .collect();
+
+        (decode, encode)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Rgb = (u8, u8, u8);
+
+    /// The matrices of Machado, Oliveira and Fernandes applied to the primaries and orange in
+    /// double precision, the lookup tables may round a channel the other way
+    #[rustfmt::skip]
+    const MACHADO: [(Deficiency, Rgb, Rgb); 12] = [
+        (Deficiency::Protanopia, (255, 0, 0), (109, 95, 0)),
+        (Deficiency::Protanopia, (0, 255, 0), (255, 229, 0)),
+        (Deficiency::Protanopia, (0, 0, 255), (0, 89, 255)),
+        (Deficiency::Protanopia, (255, 128, 0), (166, 145, 0)),
+        (Deficiency::Deuteranopia, (255, 0, 0), (163, 144, 0)),
+        (Deficiency::Deuteranopia, (0, 255, 0), (239, 214, 58)),
+        (Deficiency::Deuteranopia, (0, 0, 255), (0, 61, 251)),
+        (Deficiency::Deuteranopia, (255, 128, 0), (196, 174, 0)),
+        (Deficiency::Tritanopia, (255, 0, 0), (255, 0, 15)),
+        (Deficiency::Tritanopia, (0, 255, 0), (0, 247, 217)),
+        (Deficiency::Tritanopia, (0, 0, 255), (0, 107, 150)),
+        (Deficiency::Tritanopia, (255, 128, 0), (255, 98, 109)),
+    ];
+
+    #[test]
+    fn simulate_matches_machado() {
+        for (deficiency, color, expected) in MACHADO {
+            let (r, g, b) = to_rgb(deficiency.simulate(from_rgb(color)));
+            let (er, eg, eb) = expected;
+
+            assert!(
+                r.abs_diff(er) <= 1 && g.abs_diff(eg) <= 1 && b.abs_diff(eb) <= 1,
+                "{deficiency} of {color:?} was {:?} instead of {expected:?}",
+                (r, g, b)
+            );
+        }
+    }
+
+    #[test]
+    fn simulate_keeps_grays() {
+        for deficiency in [
+            Deficiency::Protanopia,
+            Deficiency::Deuteranopia,
+            Deficiency::Tritanopia,
+            Deficiency::Achromatopsia,
+        ] {
+            for gray in [0x000000, 0x808080, 0xFFFFFF] {
+                let (r, g, b) = to_rgb(deficiency.simulate(gray));
+                let (expected, _, _) = to_rgb(gray);
+
+                assert!(
+                    [r, g, b]
+                        .iter()
+                        .all(|channel| channel.abs_diff(expected) <= 1),
+                    "{deficiency} of {gray:06X} was {:?}",
+                    (r, g, b)
+                );
+            }
+        }
+    }
+}
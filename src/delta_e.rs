@@ -0,0 +1,185 @@
+use crate::srgb;
+
+/// The D65 white point in XYZ
+const WHITE: [f64; 3] = [0.95047, 1.0, 1.08883];
+
+/// Converts an sRGB color to CIELAB under D65
+pub fn to_lab(color: (u8, u8, u8)) -> [f64; 3] {
+    let [r, g, b] = <[u8; 3]>::from(color)
+        .map(|channel| f64::from(srgb::to_linear(f32::from(channel) / 255.0)));
+
+    let xyz = [
+        0.1804375f64.mul_add(b, 0.4124564f64.mul_add(r, 0.3575761 * g)),
+        0.0721750f64.mul_add(b, 0.2126729f64.mul_add(r, 0.7151522 * g)),
+        0.9503041f64.mul_add(b, 0.0193339f64.mul_add(r, 0.1191920 * g)),
+    ];
+
+    let [x, y, z] = [0, 1, 2].map(|axis| {
+        let t = xyz[axis] / WHITE[axis];
+        let epsilon = (6.0f64 / 29.0).powi(3);
+
+        if t > epsilon {
+            t.cbrt()
+        } else {
+            t / (3.0 * (6.0f64 / 29.0).powi(2)) + 4.0 / 29.0
+        }
+    });
+
+    [116.0f64.mul_add(y, -16.0), 500.0 * (x - y), 200.0 * (y - z)]
+}
+
+/// The CIEDE2000 color difference, where about 1 is the smallest difference people notice
+pub fn ciede2000(a: (u8, u8, u8), b: (u8, u8, u8)) -> f64 {
+    lab_difference(to_lab(a), to_lab(b))
+}
+
+/// The CIEDE2000 color difference between two CIELAB colors
+fn lab_difference([l1, a1, b1]: [f64; 3], [l2, a2, b2]: [f64; 3]) -> f64 {
+    let chroma_mean = (a1.hypot(b1) + a2.hypot(b2)) / 2.0;
+    let g = 0.5 * (1.0 - (chroma_mean.powi(7) / (chroma_mean.powi(7) + 25.0f64.powi(7))).sqrt());
+
+    let (a1, a2) = ((1.0 + g) * a1, (1.0 + g) * a2);
+    let (c1, c2) = (a1.hypot(b1), a2.hypot(b2));
+    let hue = |a: f64, b: f64| {
+        if a == 0.0 && b == 0.0 {
+            0.0
+        } else {
+            b.atan2(a).to_degrees().rem_euclid(360.0)
+        }
+    };
+    let (h1, h2) = (hue(a1, b1), hue(a2, b2));
+
+    let delta_l = l2 - l1;
+    let delta_c = c2 - c1;
+    let delta_h = if c1 * c2 == 0.0 {
+        0.0
+    } else if (h2 - h1).abs() <= 180.0 {
+        h2 - h1
+    } else if h2 <= h1 {
+        h2 - h1 + 360.0
+    } else {
+        h2 - h1 - 360.0
+    };
+    let delta_h = 2.0 * (c1 * c2).sqrt() * (delta_h / 2.0).to_radians().sin();
+
+    let l_mean = (l1 + l2) / 2.0;
+    let c_mean = (c1 + c2) / 2.0;
+    let h_mean = if c1 * c2 == 0.0 {
+        h1 + h2
+    } else if (h1 - h2).abs() <= 180.0 {
+        (h1 + h2) / 2.0
+    } else if h1 + h2 < 360.0 {
+        (h1 + h2 + 360.0) / 2.0
+    } else {
+        (h1 + h2 - 360.0) / 2.0
+    };
+
+    let cos = |degrees: f64| degrees.to_radians().cos();
+    let t = 0.20f64.mul_add(
+        -cos(4.0f64.mul_add(h_mean, -63.0)),
+        0.32f64.mul_add(
+            cos(3.0f64.mul_add(h_mean, 6.0)),
+            0.24f64.mul_add(cos(2.0 * h_mean), 0.17f64.mul_add(-cos(h_mean - 30.0), 1.0)),
+        ),
+    );
+
+    let rotation = 30.0 * (-((h_mean - 275.0) / 25.0).powi(2)).exp();
+    let r_c = 2.0 * (c_mean.powi(7) / (c_mean.powi(7) + 25.0f64.powi(7))).sqrt();
+    let lightness = (l_mean - 50.0).powi(2);
+    let s_l = 1.0 + 0.015 * lightness / (20.0 + lightness).sqrt();
+    let s_c = 0.045f64.mul_add(c_mean, 1.0);
+    let s_h = (0.015 * c_mean).mul_add(t, 1.0);
+    let r_t = -(2.0 * rotation).to_radians().sin() * r_c;
+
+    let (l, c, h) = (delta_l / s_l, delta_c / s_c, delta_h / s_h);
+
+    (r_t * c)
+        .mul_add(h, h.mul_add(h, l.mul_add(l, c * c)))
+        .sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The test data of Sharma, Wu and Dalal, "The CIEDE2000 Color-Difference Formula:
+    /// Implementation Notes, Supplementary Test Data, and Mathematical Observations" (2005)
+    #[rustfmt::skip]
+    const SHARMA: [([f64; 3], [f64; 3], f64); 34] = [
+        ([50.0, 2.6772, -79.7751], [50.0, 0.0, -82.7485], 2.0425),
+        ([50.0, 3.1571, -77.2803], [50.0, 0.0, -82.7485], 2.8615),
+        ([50.0, 2.8361, -74.0200], [50.0, 0.0, -82.7485], 3.4412),
+        ([50.0, -1.3802, -84.2814], [50.0, 0.0, -82.7485], 1.0000),
+        ([50.0, -1.1848, -84.8006], [50.0, 0.0, -82.7485], 1.0000),
+        ([50.0, -0.9009, -85.5211], [50.0, 0.0, -82.7485], 1.0000),
+        ([50.0, 0.0, 0.0], [50.0, -1.0, 2.0], 2.3669),
+        ([50.0, -1.0, 2.0], [50.0, 0.0, 0.0], 2.3669),
+        ([50.0, 2.4900, -0.0010], [50.0, -2.4900, 0.0009], 7.1792),
+        ([50.0, 2.4900, -0.0010], [50.0, -2.4900, 0.0010], 7.1792),
+        ([50.0, 2.4900, -0.0010], [50.0, -2.4900, 0.0011], 7.2195),
+        ([50.0, 2.4900, -0.0010], [50.0, -2.4900, 0.0012], 7.2195),
+        ([50.0, -0.0010, 2.4900], [50.0, 0.0009, -2.4900], 4.8045),
+        ([50.0, -0.0010, 2.4900], [50.0, 0.0010, -2.4900], 4.8045),
+        ([50.0, -0.0010, 2.4900], [50.0, 0.0011, -2.4900], 4.7461),
+        ([50.0, 2.5, 0.0], [50.0, 0.0, -2.5], 4.3065),
+        ([50.0, 2.5, 0.0], [73.0, 25.0, -18.0], 27.1492),
+        ([50.0, 2.5, 0.0], [61.0, -5.0, 29.0], 22.8977),
+        ([50.0, 2.5, 0.0], [56.0, -27.0, -3.0], 31.9030),
+        ([50.0, 2.5, 0.0], [58.0, 24.0, 15.0], 19.4535),
+        ([50.0, 2.5, 0.0], [50.0, 3.1736, 0.5854], 1.0000),
+        ([50.0, 2.5, 0.0], [50.0, 3.2972, 0.0], 1.0000),
+        ([50.0, 2.5, 0.0], [50.0, 1.8634, 0.5757], 1.0000),
+        ([50.0, 2.5, 0.0], [50.0, 3.2592, 0.3350], 1.0000),
+        ([60.2574, -34.0099, 36.2677], [60.4626, -34.1751, 39.4387], 1.2644),
+        ([63.0109, -31.0961, -5.8663], [62.8187, -29.7946, -4.0864], 1.2630),
+        ([61.2901, 3.7196, -5.3901], [61.4292, 2.2480, -4.9620], 1.8731),
+        ([35.0831, -44.1164, 3.7933], [35.0232, -40.0716, 1.5901], 1.8645),
+        ([22.7233, 20.0904, -46.6940], [23.0331, 14.9730, -42.5619], 2.0373),
+        ([36.4612, 47.8580, 18.3852], [36.2715, 50.5065, 21.2231], 1.4146),
+        ([90.8027, -2.0831, 1.4410], [91.1528, -1.6435, 0.0447], 1.4441),
+        ([90.9257, -0.5406, -0.9208], [88.6381, -0.8985, -0.7239], 1.5381),
+        ([6.7747, -0.2908, -2.4247], [5.8714, -0.0985, -2.2286], 0.6377),
+        ([2.0776, 0.0795, -1.1350], [0.9033, -0.0636, -0.5514], 0.9082),
+    ];
+
+    #[test]
+    fn matches_sharma_test_data() {
+        for (index, (first, second, expected)) in SHARMA.into_iter().enumerate() {
+            // The formula is symmetric, so both orders have to give the published difference
+            for (a, b) in [(first, second), (second, first)] {
+                let difference = lab_difference(a, b);
+
+                assert!(
+                    (difference - expected).abs() < 1e-4,
+                    "pair {}: {a:?} and {b:?} differ by {difference}, expected {expected}",
+                    index + 1
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn converts_srgb_to_lab() {
+        for (color, expected) in [
+            ((0, 0, 0), [0.0, 0.0, 0.0]),
+            ((255, 255, 255), [100.0, 0.0, 0.0]),
+            ((255, 0, 0), [53.2408, 80.0925, 67.2032]),
+            ((0, 255, 0), [87.7347, -86.1827, 83.1793]),
+            ((0, 0, 255), [32.2970, 79.1875, -107.8602]),
+        ] {
+            let lab = to_lab(color);
+
+            for (channel, expected) in lab.into_iter().zip(expected) {
+                assert!(
+                    (channel - expected).abs() < 1e-2,
+                    "{color:?} converted to {lab:?}, expected {expected:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn identical_colors_do_not_differ() {
+        assert_eq!(ciede2000((12, 34, 56), (12, 34, 56)), 0.0);
+    }
+}
@@ -0,0 +1,924 @@
+use std::thread;
+
+use image::{DynamicImage, Rgb, RgbImage};
+
+use crate::font;
+
+/// A rectangle of a framebuffer, in pixels
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+const CHECKERBOARD: [u32; 2] = [0x666666, 0x999999];
+
+/// What the cells of the lens show about their pixel
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LensView {
+    #[default]
+    Normal,
+    Red,
+    Green,
+    Blue,
+    Luma,
+    /// How saturated each pixel is as a heatmap
+    Saturation,
+    /// How far each pixel is from the center one, with even a difference of one standing out
+    Difference,
+}
+
+impl LensView {
+    const ALL: [Self; 7] = [
+        Self::Normal,
+        Self::Red,
+        Self::Green,
+        Self::Blue,
+        Self::Luma,
+        Self::Saturation,
+        Self::Difference,
+    ];
+
+    #[must_use]
+    pub fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|&view| view == self).unwrap();
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Normal => "normal",
+            Self::Red => "red",
+            Self::Green => "green",
+            Self::Blue => "blue",
+            Self::Luma => "luma",
+            Self::Saturation => "saturation",
+            Self::Difference => "difference",
+        }
+    }
+
+    /// The color a cell is shown as
+    pub fn apply(self, pixel: u32, center: u32) -> u32 {
+        let (r, g, b) = to_rgb(pixel);
+
+        match self {
+            Self::Normal => pixel,
+            Self::Red => gray(r.into()),
+            Self::Green => gray(g.into()),
+            Self::Blue => gray(b.into()),
+            Self::Luma => gray(luma(pixel)),
+            Self::Saturation => {
+                let max = r.max(g).max(b) as u32;
+                let min = r.min(g).min(b) as u32;
+
+                heatmap((max - min) * 255 / max.max(1))
+            }
+            Self::Difference => {
+                let (center_r, center_g, center_b) = to_rgb(center);
+                let difference = r.abs_diff(center_r) as u32
+                    + g.abs_diff(center_g) as u32
+                    + b.abs_diff(center_b) as u32;
+
+                if difference == 0 {
+                    0x000000
+                } else {
+                    heatmap((64 + difference * 3).min(255))
+                }
+            }
+        }
+    }
+}
+
+/// How the lens is drawn
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LensStyle {
+    pub view: LensView,
+    pub grid: bool,
+    /// Overrides the grid color, which otherwise contrasts with the average of the lens
+    pub grid_color: Option<u32>,
+    pub circle: bool,
+    pub shadow: bool,
+    /// How far the center of the lens is moved from the cursor
+    pub offset: (i32, i32),
+    /// How many cells around the center one are outlined as being sampled by a pick
+    pub sample_radius: u32,
+}
+
+impl Default for LensStyle {
+    fn default() -> Self {
+        Self {
+            view: LensView::Normal,
+            grid: true,
+            grid_color: None,
+            circle: false,
+            shadow: false,
+            offset: (0, 0),
+            sample_radius: 0,
+        }
+    }
+}
+
+const SHADOW_OFFSET: i64 = 6;
+
+/// Where a lens ended up and the grid color picked to contrast with what it shows
+#[derive(Debug, Clone)]
+pub struct DrawnLens {
+    pub rect: Rect,
+    pub border_color: u32,
+    pub cells: Vec<LensCell>,
+}
+
+/// A fully visible cell of the lens that shows a captured pixel
+#[derive(Debug, Clone, Copy)]
+pub struct LensCell {
+    /// The inside of the cell, without the grid
+    pub rect: Rect,
+    pub color: u32,
+    /// What the cell looks like in the current view
+    pub shown: u32,
+}
+
+/// The only copy of a monitor's capture, as 0RGB like softbuffer expects.
+///
+/// Overlays are drawn straight into the capture, the pixels they cover are saved first so they
+/// can be restored on the next frame and still be read in the meantime.
+///
+/// A filter over the whole screen moves the capture aside and shows a filtered copy instead.
+pub struct Framebuffer {
+    width: u32,
+    height: u32,
+    pixels: Vec<u32>,
+    /// The capture while `pixels` holds a filtered copy of it, which is a second copy of the whole
+    /// monitor for as long as a filter is shown
+    capture: Option<Vec<u32>>,
+    /// Rectangles drawn over in order, with where their original pixels start in `saved`
+    damage: Vec<(Rect, usize)>,
+    saved: Vec<u32>,
+    /// Rectangles that changed since the frame was last presented, `None` if all of it did
+    changed: Option<Vec<Rect>>,
+    /// What changed in the frame presented before that, for buffers that are two frames old
+    last_changed: Option<Vec<Rect>>,
+}
+
+impl Framebuffer {
+    pub fn new(width: u32, height: u32, pixels: Vec<u32>) -> Self {
+        assert_eq!(pixels.len(), width as usize * height as usize);
+
+        Self {
+            width,
+            height,
+            pixels,
+            capture: None,
+            damage: Vec::new(),
+            saved: Vec::new(),
+            changed: None,
+            last_changed: None,
+        }
+    }
+
+    /// Packs an area of an image, which has to lie within it
+    pub fn from_image_area(image: &DynamicImage, area: Rect) -> Self {
+        let (bytes, channels): (&[u8], usize) = match image {
+            DynamicImage::ImageRgb8(image) => (image, 3),
+            DynamicImage::ImageRgba8(image) => (image, 4),
+            other => return Self::from_image_area(&DynamicImage::ImageRgb8(other.to_rgb8()), area),
+        };
+
+        let stride = image.width() as usize * channels;
+
+        let pixels = (area.y..area.y + area.height)
+            .flat_map(|y| {
+                let start = y as usize * stride + area.x as usize * channels;
+                let row = &bytes[start..start + area.width as usize * channels];
+
+                row.chunks_exact(channels)
+                    .map(|rgb| from_rgb((rgb[0], rgb[1], rgb[2])))
+            })
+            .collect();
+
+        Self::new(area.width, area.height, pixels)
+    }
+
+    pub fn from_image(image: &DynamicImage) -> Self {
+        let area = Rect {
+            x: 0,
+            y: 0,
+            width: image.width(),
+            height: image.height(),
+        };
+
+        Self::from_image_area(image, area)
+    }
+
+    /// Converts the frame, including anything drawn over it, back into an image
+    pub fn to_image(&self) -> RgbImage {
+        RgbImage::from_fn(self.width, self.height, |x, y| {
+            Rgb(to_rgb(self.pixels[self.index(x, y)]).into())
+        })
+    }
+
+    /// Copies an area of the capture without anything drawn over it or any filter, the area has
+    /// to lie within it
+    pub fn capture_crop(&self, area: Rect) -> Self {
+        let mut crop = Self::new(
+            area.width,
+            area.height,
+            vec![0; area.width as usize * area.height as usize],
+        );
+        self.copy_capture(area, &mut crop, 0, 0);
+
+        crop
+    }
+
+    /// Copies an area of the capture without anything drawn over it or any filter into `target`
+    /// with its top left corner at `x`, `y`. The area has to lie within both framebuffers
+    pub fn copy_capture(&self, area: Rect, target: &mut Self, x: u32, y: u32) {
+        let capture = self.capture.as_deref().unwrap_or(&self.pixels);
+        let width = area.width as usize;
+
+        for row in 0..area.height {
+            let from = self.index(area.x, area.y + row);
+            let to = target.index(x, y + row);
+
+            target.pixels[to..to + width].copy_from_slice(&capture[from..from + width]);
+        }
+
+        if self.capture.is_some() {
+            return;
+        }
+
+        // Overlays are drawn over the capture itself without a filter, so what they covered is
+        // put back. The first save of a pixel is the only one that still has its captured value,
+        // so it has to be put back last
+        for &(rect, start) in self.damage.iter().rev() {
+            let left = rect.x.max(area.x);
+            let top = rect.y.max(area.y);
+            let right = (rect.x + rect.width).min(area.x + area.width);
+            let bottom = (rect.y + rect.height).min(area.y + area.height);
+
+            if left >= right {
+                continue;
+            }
+
+            let width = (right - left) as usize;
+
+            for row in top..bottom {
+                let from = start + ((row - rect.y) * rect.width + left - rect.x) as usize;
+                let to = target.index(x + left - area.x, y + row - area.y);
+
+                target.pixels[to..to + width].copy_from_slice(&self.saved[from..from + width]);
+            }
+        }
+    }
+
+    /// Copies an area of the frame, which has to lie within it
+    pub fn crop(&self, area: Rect) -> Self {
+        let pixels = (area.y..area.y + area.height)
+            .flat_map(|y| {
+                let start = self.index(area.x, y);
+                self.pixels[start..start + area.width as usize]
+                    .iter()
+                    .copied()
+            })
+            .collect();
+
+        Self::new(area.width, area.height, pixels)
+    }
+
+    /// Scales a capture that was taken in logical coordinates to a monitor's physical size.
+    ///
+    /// Nearest neighbour keeps every pixel an actual color from the screen.
+    pub fn resized(self, width: u32, height: u32) -> Self {
+        if (width, height) == (self.width, self.height) {
+            return self;
+        }
+
+        if self.pixels.is_empty() {
+            return Self::new(width, height, vec![0; width as usize * height as usize]);
+        }
+
+        let mut pixels = Vec::with_capacity(width as usize * height as usize);
+
+        for y in 0..height {
+            let source_y = (y as u64 * self.height as u64 / height as u64) as u32;
+
+            for x in 0..width {
+                let source_x = (x as u64 * self.width as u64 / width as u64) as u32;
+                pixels.push(self.pixels[self.index(source_x, source_y)]);
+            }
+        }
+
+        Self::new(width, height, pixels)
+    }
+
+    pub const fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub const fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// The frame to present, including the lens
+    pub fn pixels(&self) -> &[u32] {
+        &self.pixels
+    }
+
+    /// Brings a `target` buffer that was presented `age` frames ago up to date with the frame, and
+    /// returns the rectangles that changed since the last one.
+    ///
+    /// Only what changed is copied, so moving the lens costs the same on any monitor. Buffers of
+    /// an unknown age, like new ones with an age of 0, get the whole frame.
+    pub fn present_into(&mut self, target: &mut [u32], age: u8) -> Vec<Rect> {
+        let whole = Rect {
+            x: 0,
+            y: 0,
+            width: self.width,
+            height: self.height,
+        };
+
+        let stale = match (age, &self.changed, &self.last_changed) {
+            (1, Some(changed), _) => Some(changed.clone()),
+            (2, Some(changed), Some(last_changed)) => Some([&changed[..], last_changed].concat()),
+            _ => None,
+        };
+
+        for rect in stale.unwrap_or_else(|| vec![whole]) {
+            let width = rect.width as usize;
+
+            for y in rect.y..rect.y + rect.height {
+                let start = self.index(rect.x, y);
+                target[start..start + width].copy_from_slice(&self.pixels[start..start + width]);
+            }
+        }
+
+        let changed = self.changed.replace(Vec::new());
+        self.last_changed = changed.clone();
+
+        changed.unwrap_or_else(|| vec![whole])
+    }
+
+    /// A pixel of the capture, ignoring anything drawn over it and any filter
+    pub fn capture_pixel(&self, x: i64, y: i64) -> Option<u32> {
+        match &self.capture {
+            Some(capture) => {
+                let x = u32::try_from(x).ok().filter(|&x| x < self.width)?;
+                let y = u32::try_from(y).ok().filter(|&y| y < self.height)?;
+
+                Some(capture[self.index(x, y)])
+            }
+            None => self.shown_pixel(x, y),
+        }
+    }
+
+    /// A pixel as it is shown through the current filter, ignoring anything drawn over it
+    pub fn shown_pixel(&self, x: i64, y: i64) -> Option<u32> {
+        let x = u32::try_from(x).ok().filter(|&x| x < self.width)?;
+        let y = u32::try_from(y).ok().filter(|&y| y < self.height)?;
+
+        // The first save of a pixel is the only one that still has its captured value
+        let saved = self.damage.iter().find_map(|&(rect, start)| {
+            let inside = (rect.x..rect.x + rect.width).contains(&x)
+                && (rect.y..rect.y + rect.height).contains(&y);
+
+            inside.then(|| self.saved[start + ((y - rect.y) * rect.width + x - rect.x) as usize])
+        });
+
+        Some(saved.unwrap_or_else(|| self.pixels[self.index(x, y)]))
+    }
+
+    /// Shows every pixel of the capture through `filter`, or the capture itself for `None`.
+    ///
+    /// Everything drawn over the frame is cleared.
+    ///
+    /// While a filter is shown the frame holds the capture and the filtered copy, twice the memory
+    /// of the capture alone, so that picks and saved captures still see the real colors and
+    /// switching filters does not need a new screenshot. Going back to `None` frees the copy.
+    pub fn set_filter(&mut self, filter: Option<impl Fn(u32) -> u32 + Sync>) {
+        self.clear();
+        self.changed = None;
+
+        let Some(filter) = filter else {
+            if let Some(capture) = self.capture.take() {
+                self.pixels = capture;
+            }
+            return;
+        };
+
+        let capture = self.capture.get_or_insert_with(|| self.pixels.clone());
+
+        let threads = thread::available_parallelism().map_or(1, usize::from);
+        let chunk_size = capture.len().div_ceil(threads).max(1);
+
+        thread::scope(|scope| {
+            for (filtered, captured) in self
+                .pixels
+                .chunks_mut(chunk_size)
+                .zip(capture.chunks(chunk_size))
+            {
+                let filter = &filter;
+
+                scope.spawn(move || {
+                    // Screens are mostly runs of the same color, so this skips most of the work
+                    let mut last = None;
+
+                    for (filtered, &captured) in filtered.iter_mut().zip(captured) {
+                        *filtered = match last {
+                            Some((from, to)) if from == captured => to,
+                            _ => filter(captured),
+                        };
+                        last = Some((captured, *filtered));
+                    }
+                });
+            }
+        });
+    }
+
+    const fn index(&self, x: u32, y: u32) -> usize {
+        y as usize * self.width as usize + x as usize
+    }
+
+    /// Saves the pixels of a rectangle before it is drawn over
+    fn save(&mut self, rect: Rect) {
+        self.damage.push((rect, self.saved.len()));
+        if let Some(changed) = &mut self.changed {
+            changed.push(rect);
+        }
+
+        for y in rect.y..rect.y + rect.height {
+            let start = self.index(rect.x, y);
+
+            self.saved
+                .extend_from_slice(&self.pixels[start..start + rect.width as usize]);
+        }
+    }
+
+    /// Clips a rectangle to the framebuffer, returns `None` if none of it is visible
+    fn clip(&self, x: i64, y: i64, width: u32, height: u32) -> Option<Rect> {
+        let left = x.clamp(0, self.width as i64);
+        let top = y.clamp(0, self.height as i64);
+        let right = (x + width as i64).clamp(0, self.width as i64);
+        let bottom = (y + height as i64).clamp(0, self.height as i64);
+
+        (left < right && top < bottom).then_some(Rect {
+            x: left as u32,
+            y: top as u32,
+            width: (right - left) as u32,
+            height: (bottom - top) as u32,
+        })
+    }
+
+    /// Colors every pixel of an already clipped rectangle, without saving what was there
+    fn paint(&mut self, rect: Rect, color: u32) {
+        for y in rect.y..rect.y + rect.height {
+            let start = self.index(rect.x, y);
+            self.pixels[start..start + rect.width as usize].fill(color);
+        }
+    }
+
+    pub fn fill_rect(&mut self, x: i64, y: i64, width: u32, height: u32, color: u32) {
+        let Some(rect) = self.clip(x, y, width, height) else {
+            return;
+        };
+
+        self.save(rect);
+        self.paint(rect, color);
+    }
+
+    /// Draws a one pixel wide line between two points, which may lie off the framebuffer
+    pub fn draw_line(&mut self, from: (i64, i64), to: (i64, i64), color: u32) {
+        let (dx, dy) = ((to.0 - from.0).abs(), -(to.1 - from.1).abs());
+        let (step_x, step_y) = ((to.0 - from.0).signum(), (to.1 - from.1).signum());
+
+        let (mut x, mut y) = from;
+        let mut error = dx + dy;
+
+        // Every row of the line is saved and painted as one span
+        let mut span = (x, x, y);
+
+        loop {
+            if y != span.2 {
+                self.fill_span(span, color);
+                span = (x, x, y);
+            }
+            span = (span.0.min(x), span.1.max(x), y);
+
+            if (x, y) == to {
+                break;
+            }
+
+            let doubled = 2 * error;
+            if doubled >= dy {
+                error += dy;
+                x += step_x;
+            }
+            if doubled <= dx {
+                error += dx;
+                y += step_y;
+            }
+        }
+
+        self.fill_span(span, color);
+    }
+
+    fn fill_span(&mut self, (left, right, y): (i64, i64, i64), color: u32) {
+        self.fill_rect(left, y, (right - left + 1) as u32, 1, color);
+    }
+
+    /// Tints the pixels set in `mask`, a `width` wide rectangle starting at `x`, `y` row by row,
+    /// and outlines the edges between them and the rest
+    pub fn highlight(&mut self, x: i64, y: i64, width: u32, mask: &[bool], color: u32) {
+        let height = (mask.len() / width.max(1) as usize) as u32;
+        let Some(bounds) = self.clip(x, y, width, height) else {
+            return;
+        };
+
+        self.save(bounds);
+
+        let is_set = |mask_x: i64, mask_y: i64| {
+            (0..width as i64).contains(&mask_x)
+                && (0..height as i64).contains(&mask_y)
+                && mask[(mask_y * width as i64 + mask_x) as usize]
+        };
+
+        for pixel_y in bounds.y..bounds.y + bounds.height {
+            for pixel_x in bounds.x..bounds.x + bounds.width {
+                let (mask_x, mask_y) = (pixel_x as i64 - x, pixel_y as i64 - y);
+
+                if !is_set(mask_x, mask_y) {
+                    continue;
+                }
+
+                let inside = is_set(mask_x - 1, mask_y)
+                    && is_set(mask_x + 1, mask_y)
+                    && is_set(mask_x, mask_y - 1)
+                    && is_set(mask_x, mask_y + 1);
+
+                let index = self.index(pixel_x, pixel_y);
+                self.pixels[index] = if inside {
+                    mix(self.pixels[index], color)
+                } else {
+                    color
+                };
+            }
+        }
+    }
+
+    /// Draws a cross 9 pixels wide centered on `x`, `y`
+    pub fn draw_cross(&mut self, x: i64, y: i64, color: u32) {
+        self.fill_rect(x - 4, y, 9, 1, color);
+        self.fill_rect(x, y - 4, 1, 9, color);
+    }
+
+    /// Draws a line of text with its top left corner at `x`, `y`, each font pixel `scale` wide
+    pub fn draw_text(&mut self, x: i64, y: i64, text: &str, scale: u32, color: u32) {
+        let width = font::text_width(text, scale);
+        let Some(bounds) = self.clip(x, y, width, font::text_height(scale)) else {
+            return;
+        };
+
+        self.save(bounds);
+
+        let advance = ((font::GLYPH_WIDTH + font::GLYPH_SPACING) * scale) as i64;
+
+        for (glyph_x, character) in (x..).step_by(advance as usize).zip(text.chars()) {
+            let glyph = font::glyph(character);
+
+            for column in 0..font::GLYPH_WIDTH {
+                for row in 0..font::GLYPH_HEIGHT {
+                    if !font::is_set(&glyph, column, row) {
+                        continue;
+                    }
+
+                    let dot_x = glyph_x + (column * scale) as i64;
+                    let dot_y = y + (row * scale) as i64;
+
+                    if let Some(dot) = self.clip(dot_x, dot_y, scale, scale) {
+                        self.paint(dot, color);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Restores everything drawn over the capture
+    pub fn clear(&mut self) {
+        while let Some((rect, saved_start)) = self.damage.pop() {
+            if let Some(changed) = &mut self.changed {
+                changed.push(rect);
+            }
+
+            for y in 0..rect.height {
+                let start = self.index(rect.x, rect.y + y);
+                let saved = saved_start + (y * rect.width) as usize;
+
+                self.pixels[start..start + rect.width as usize]
+                    .copy_from_slice(&self.saved[saved..saved + rect.width as usize]);
+            }
+        }
+
+        self.saved.clear();
+    }
+
+    /// Draws a lens of `zoom_size` cells, each `zoom` pixels wide, around `mouse_pos` with the
+    /// cell that would be picked outlined.
+    ///
+    /// Only the lens is touched, so the cost does not depend on the size of the frame. It is drawn
+    /// over what is already there, call `clear` first to move it.
+    pub fn draw_lens(
+        &mut self,
+        mouse_pos: (u32, u32),
+        zoom: u32,
+        zoom_size: u32,
+        style: &LensStyle,
+    ) -> DrawnLens {
+        let cells = self.lens_pixels(mouse_pos, zoom_size);
+
+        self.draw_lens_pixels(mouse_pos, &cells, zoom, zoom_size, style)
+    }
+
+    /// The pixels the `zoom_size` cells of a lens around `mouse_pos` show row by row, `None` for
+    /// the ones past the edge of the screen
+    pub fn lens_pixels(&self, mouse_pos: (u32, u32), zoom_size: u32) -> Vec<Option<u32>> {
+        let square_halfway = (zoom_size / 2) as i64;
+
+        (0..zoom_size * zoom_size)
+            .map(|index| {
+                let (x, y) = (index % zoom_size, index / zoom_size);
+
+                self.shown_pixel(
+                    mouse_pos.0 as i64 + x as i64 - square_halfway,
+                    mouse_pos.1 as i64 + y as i64 - square_halfway,
+                )
+            })
+            .collect()
+    }
+
+    /// Draws a lens like `draw_lens` does, but of `cells` from `lens_pixels` instead of what is
+    /// under it now
+    pub fn draw_lens_pixels(
+        &mut self,
+        mouse_pos: (u32, u32),
+        cells: &[Option<u32>],
+        zoom: u32,
+        zoom_size: u32,
+        style: &LensStyle,
+    ) -> DrawnLens {
+        assert_eq!(cells.len(), zoom_size as usize * zoom_size as usize);
+
+        let center = cells[(zoom_size / 2 * zoom_size + zoom_size / 2) as usize];
+        let shown = cells
+            .iter()
+            .map(|cell| cell.map(|pixel| style.view.apply(pixel, center.unwrap_or(pixel))))
+            .collect::<Vec<_>>();
+
+        let (total_light_value, pixels_in_bounds) = shown
+            .iter()
+            .flatten()
+            .fold((0, 0), |(total, count), &pixel| {
+                (total + luma(pixel), count + 1)
+            });
+
+        let border_color = style
+            .grid_color
+            .unwrap_or_else(|| gray(255 - total_light_value / pixels_in_bounds.max(1)));
+
+        // An additional line for the final x and y grid lines
+        let zoomed_size: u32 = zoom_size * zoom + u32::from(style.grid);
+        let zoom_halfway: u32 = zoomed_size / 2;
+
+        let rect = Rect {
+            x: lens_origin(
+                mouse_pos.0 as i64 + style.offset.0 as i64,
+                zoom_halfway,
+                zoomed_size,
+                self.width,
+            ),
+            y: lens_origin(
+                mouse_pos.1 as i64 + style.offset.1 as i64,
+                zoom_halfway,
+                zoomed_size,
+                self.height,
+            ),
+            width: zoomed_size.min(self.width),
+            height: zoomed_size.min(self.height),
+        };
+
+        if style.shadow {
+            self.draw_shadow(rect, style.circle);
+        }
+
+        self.save(rect);
+
+        for lens_y in 0..rect.height {
+            let start = self.index(rect.x, rect.y + lens_y);
+            let row = &mut self.pixels[start..start + rect.width as usize];
+
+            for (lens_x, pixel) in (0..).zip(row) {
+                let edge = if style.circle {
+                    match circle_distance(lens_x, lens_y, zoomed_size) {
+                        Some(distance) => distance <= 1,
+                        None => continue,
+                    }
+                } else {
+                    false
+                };
+
+                *pixel = if edge || (style.grid && (lens_x % zoom == 0 || lens_y % zoom == 0)) {
+                    border_color
+                } else {
+                    let cell_x = (lens_x / zoom).min(zoom_size - 1);
+                    let cell_y = (lens_y / zoom).min(zoom_size - 1);
+
+                    // Cells past the edge of the screen get a checkerboard so the lens keeps its
+                    // shape
+                    shown[(cell_y * zoom_size + cell_x) as usize]
+                        .unwrap_or(CHECKERBOARD[((cell_x + cell_y) % 2) as usize])
+                };
+            }
+        }
+
+        // Sits on the grid lines around the sampled cells when there are any
+        let sample_radius = style.sample_radius.min(zoom_size / 2);
+        let target = Rect {
+            x: rect.x + (zoom_size / 2 - sample_radius) * zoom,
+            y: rect.y + (zoom_size / 2 - sample_radius) * zoom,
+            width: (2 * sample_radius + 1) * zoom + u32::from(style.grid),
+            height: (2 * sample_radius + 1) * zoom + u32::from(style.grid),
+        };
+        let center_shown = shown[(zoom_size / 2 * zoom_size + zoom_size / 2) as usize];
+
+        self.outline(
+            target,
+            (zoom / 8).clamp(1, 4),
+            contrasting(center_shown.unwrap_or(CHECKERBOARD[0])),
+        );
+
+        let grid = u32::from(style.grid);
+        let cells = (0..zoom_size * zoom_size)
+            .filter_map(|index| {
+                let (x, y) = (index % zoom_size, index / zoom_size);
+
+                let cell = Rect {
+                    x: x * zoom + grid,
+                    y: y * zoom + grid,
+                    width: zoom - grid,
+                    height: zoom - grid,
+                };
+
+                let visible = cell.x + cell.width <= rect.width
+                    && cell.y + cell.height <= rect.height
+                    && (!style.circle
+                        || [
+                            (cell.x, cell.y),
+                            (cell.x + cell.width - 1, cell.y),
+                            (cell.x, cell.y + cell.height - 1),
+                            (cell.x + cell.width - 1, cell.y + cell.height - 1),
+                        ]
+                        .into_iter()
+                        .all(|(x, y)| circle_distance(x, y, zoomed_size).is_some()));
+
+                let cell = Rect {
+                    x: rect.x + cell.x,
+                    y: rect.y + cell.y,
+                    ..cell
+                };
+
+                visible.then_some(LensCell {
+                    rect: cell,
+                    color: cells[index as usize]?,
+                    shown: shown[index as usize]?,
+                })
+            })
+            .collect();
+
+        DrawnLens {
+            rect,
+            border_color,
+            cells,
+        }
+    }
+
+    /// Darkens the area the lens is about to cover, moved down and to the right
+    fn draw_shadow(&mut self, lens: Rect, circle: bool) {
+        let Some(shadow) = self.clip(
+            lens.x as i64 + SHADOW_OFFSET,
+            lens.y as i64 + SHADOW_OFFSET,
+            lens.width,
+            lens.height,
+        ) else {
+            return;
+        };
+
+        self.save(shadow);
+
+        for y in 0..shadow.height {
+            let start = self.index(shadow.x, shadow.y + y);
+            let row = &mut self.pixels[start..start + shadow.width as usize];
+
+            for (x, pixel) in (0..).zip(row) {
+                if circle && circle_distance(x, y, lens.width).is_none() {
+                    continue;
+                }
+
+                *pixel = (*pixel >> 1) & 0x7F7F7F;
+            }
+        }
+    }
+
+    /// Draws the inside border of a rectangle that is already saved
+    fn outline(&mut self, rect: Rect, thickness: u32, color: u32) {
+        let thickness = thickness.min(rect.width / 2).min(rect.height / 2).max(1);
+        let right = rect.x + rect.width - thickness;
+        let bottom = rect.y + rect.height - thickness;
+
+        for (x, y, width, height) in [
+            (rect.x, rect.y, rect.width, thickness),
+            (rect.x, bottom, rect.width, thickness),
+            (rect.x, rect.y, thickness, rect.height),
+            (right, rect.y, thickness, rect.height),
+        ] {
+            if let Some(edge) = self.clip(x.into(), y.into(), width, height) {
+                self.paint(edge, color);
+            }
+        }
+    }
+}
+
+/// How many pixels `x`, `y` is inside the edge of a circle filling a `size` wide square, `None` if
+/// it is outside of it
+fn circle_distance(x: u32, y: u32, size: u32) -> Option<u32> {
+    // Doubled so the center of a pixel and of an even sized square are whole numbers
+    let dx = 2 * x as i64 + 1 - size as i64;
+    let dy = 2 * y as i64 + 1 - size as i64;
+    let distance = ((dx * dx + dy * dy) as f64).sqrt();
+
+    (distance <= size as f64).then(|| ((size as f64 - distance) / 2.0) as u32)
+}
+
+/// Where the lens starts along one axis, shifted away from the edges so it stays fully on screen
+fn lens_origin(center: i64, zoom_halfway: u32, zoomed_size: u32, screen_size: u32) -> u32 {
+    (center - zoom_halfway as i64)
+        .min(screen_size as i64 - zoomed_size as i64)
+        .max(0) as u32
+}
+
+pub const fn to_rgb(pixel: u32) -> (u8, u8, u8) {
+    ((pixel >> 16) as u8, (pixel >> 8) as u8, pixel as u8)
+}
+
+pub const fn from_rgb((r, g, b): (u8, u8, u8)) -> u32 {
+    (r as u32) << 16 | (g as u32) << 8 | b as u32
+}
+
+const fn gray(value: u32) -> u32 {
+    value << 16 | value << 8 | value
+}
+
+/// Black through blue, red and yellow to white for `value` from 0 to 255
+fn heatmap(value: u32) -> u32 {
+    const STOPS: [(u8, u8, u8); 5] = [
+        (0, 0, 0),
+        (0, 0, 255),
+        (255, 0, 0),
+        (255, 255, 0),
+        (255, 255, 255),
+    ];
+
+    let position = value.min(255) * (STOPS.len() as u32 - 1);
+    let (index, fraction) = ((position / 255) as usize, position % 255);
+
+    let (from, to) = (STOPS[index], STOPS[(index + 1).min(STOPS.len() - 1)]);
+    let mix =
+        |from: u8, to: u8| ((from as u32 * (255 - fraction) + to as u32 * fraction) / 255) as u8;
+
+    from_rgb((mix(from.0, to.0), mix(from.1, to.1), mix(from.2, to.2)))
+}
+
+/// Halfway between two 0RGB pixels
+const fn mix(a: u32, b: u32) -> u32 {
+    ((a >> 1) & 0x7F7F7F) + ((b >> 1) & 0x7F7F7F)
+}
+
+/// A quarter as bright as a 0RGB pixel
+pub const fn dim(pixel: u32) -> u32 {
+    (pixel >> 2) & 0x3F3F3F
+}
+
+/// Black or white, whichever stands out more against `pixel`
+pub const fn contrasting(pixel: u32) -> u32 {
+    if luma(pixel) >= 128 {
+        0x000000
+    } else {
+        0xFFFFFF
+    }
+}
+
+/// The same luma `image` computes, for a 0RGB pixel
+pub const fn luma(pixel: u32) -> u32 {
+    let (r, g, b) = ((pixel >> 16) & 0xFF, (pixel >> 8) & 0xFF, pixel & 0xFF);
+
+    (2126 * r + 7152 * g + 722 * b) / 10000
+}
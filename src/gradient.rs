@@ -0,0 +1,155 @@
+use winit::dpi::PhysicalPosition;
+
+/// The overlay color of the line a gradient is sampled along
+pub const GRADIENT_COLOR: u32 = 0x00FFFF;
+
+/// How far in any channel the colors between two stops may be from a straight blend of them
+const STOP_TOLERANCE: f64 = 3.0;
+
+/// The colors along a line dragged across the screen
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Gradient {
+    pub from: PhysicalPosition<i32>,
+    pub to: PhysicalPosition<i32>,
+    /// A color for every pixel from `from` to `to`
+    pub colors: Vec<(u8, u8, u8)>,
+}
+
+impl Gradient {
+    /// The direction of the line as a CSS angle, which starts pointing up and turns clockwise
+    pub fn angle(&self) -> f64 {
+        let dx = (self.to.x - self.from.x) as f64;
+        let dy = (self.to.y - self.from.y) as f64;
+
+        dx.atan2(-dy).to_degrees().rem_euclid(360.0)
+    }
+
+    /// The fewest colors that blend into all of the sampled ones, with where they are from 0 to 1
+    pub fn stops(&self) -> Vec<(f64, (u8, u8, u8))> {
+        let last = self.colors.len().saturating_sub(1);
+
+        let mut indices = vec![0, last];
+        self.split(0, last, &mut indices);
+        indices.sort_unstable();
+        indices.dedup();
+
+        indices
+            .into_iter()
+            .filter_map(|index| {
+                let position = if last == 0 {
+                    0.0
+                } else {
+                    index as f64 / last as f64
+                };
+
+                Some((position, *self.colors.get(index)?))
+            })
+            .collect()
+    }
+
+    /// Adds a stop at the sample furthest from a blend of `start` and `end` until every sample
+    /// is close enough to one, like Ramer-Douglas-Peucker does for lines.
+    ///
+    /// The spans still to check are kept on a stack, as long lines would recurse too deep.
+    fn split(&self, start: usize, end: usize, indices: &mut Vec<usize>) {
+        let mut spans = vec![(start, end)];
+
+        while let Some((start, end)) = spans.pop() {
+            if end <= start + 1 {
+                continue;
+            }
+
+            let (first, last) = (self.colors[start], self.colors[end]);
+
+            let furthest = (start + 1..end)
+                .map(|index| {
+                    let amount = (index - start) as f64 / (end - start) as f64;
+                    let blend =
+                        |from: u8, to: u8| (to as f64 - from as f64).mul_add(amount, from as f64);
+
+                    let color = self.colors[index];
+                    let error = (color.0 as f64 - blend(first.0, last.0))
+                        .abs()
+                        .max((color.1 as f64 - blend(first.1, last.1)).abs())
+                        .max((color.2 as f64 - blend(first.2, last.2)).abs());
+
+                    (index, error)
+                })
+                .max_by(|a, b| a.1.total_cmp(&b.1));
+
+            if let Some((index, error)) = furthest {
+                if error > STOP_TOLERANCE {
+                    indices.push(index);
+                    spans.push((start, index));
+                    spans.push((index, end));
+                }
+            }
+        }
+    }
+
+    /// A CSS `linear-gradient`, with every stop as many pixels from the start as it is along the
+    /// dragged line.
+    ///
+    /// Percentages would stretch the dragged part over the whole element, in pixels it keeps its
+    /// size and the last color carries on past it.
+    pub fn css(&self) -> String {
+        let length = ((self.to.x - self.from.x) as f64).hypot((self.to.y - self.from.y) as f64);
+
+        let stops = self
+            .stops()
+            .into_iter()
+            .map(|(position, (r, g, b))| {
+                format!(", #{r:02X}{g:02X}{b:02X} {:.0}px", position * length)
+            })
+            .collect::<String>();
+
+        format!("linear-gradient({:.0}deg{stops})", self.angle())
+    }
+}
+
+/// Every pixel from `from` to `to`, as many as the longer axis has
+pub fn line_positions(
+    from: PhysicalPosition<i32>,
+    to: PhysicalPosition<i32>,
+) -> impl Iterator<Item = PhysicalPosition<i32>> {
+    let (dx, dy) = (to.x - from.x, to.y - from.y);
+    let steps = dx.unsigned_abs().max(dy.unsigned_abs()).max(1) as f64;
+
+    (0..=steps as i32).map(move |step| {
+        let amount = step as f64 / steps;
+
+        PhysicalPosition::new(
+            (dx as f64).mul_add(amount, from.x as f64).round() as i32,
+            (dy as f64).mul_add(amount, from.y as f64).round() as i32,
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn css_places_stops_in_pixels_along_the_line() {
+        let from = PhysicalPosition::new(10, 20);
+        let to = PhysicalPosition::new(110, 20);
+        // Black to red and then to white, with the turn at 3/4 of the line
+        let colors = (0..=100)
+            .map(|step: u32| match step {
+                0..=75 => ((step * 255 / 75) as u8, 0, 0),
+                _ => (
+                    255,
+                    ((step - 75) * 255 / 25) as u8,
+                    ((step - 75) * 255 / 25) as u8,
+                ),
+            })
+            .collect();
+
+        let gradient = Gradient { from, to, colors };
+
+        assert_eq!(
+            gradient.css(),
+            "linear-gradient(90deg, #000000 0px, #FF0000 75px, #FFFFFF 100px)"
+        );
+    }
+}
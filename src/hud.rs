@@ -0,0 +1,129 @@
+use crate::args::ColorFormat;
+use crate::font;
+use crate::framebuffer::{self, Framebuffer, LensCell, Rect};
+
+/// The 5x7 font is hard to read at its native size
+pub const SCALE: u32 = 2;
+const PADDING: u32 = 4;
+const LINE_SPACING: u32 = 3;
+/// Space between the label and what it is attached to
+const GAP: u32 = 4;
+
+/// Draws a label with a line of text per entry of `lines` under `anchor`, or above it if there is
+/// no space left below. Nothing is drawn without any lines.
+///
+/// A swatch of the given color is put in front of the text, outlined so it stays visible on a
+/// background of the same color.
+pub fn draw_label(
+    framebuffer: &mut Framebuffer,
+    anchor: Rect,
+    lines: &[String],
+    swatch: Option<u32>,
+    background: u32,
+) {
+    if lines.is_empty() {
+        return;
+    }
+
+    let line_height = font::text_height(SCALE);
+    let text_height = lines.len() as u32 * (line_height + LINE_SPACING) - LINE_SPACING;
+    let text_width = lines
+        .iter()
+        .map(|line| font::text_width(line, SCALE))
+        .max()
+        .unwrap_or(0);
+
+    let swatch_width = swatch.map_or(0, |_| text_height + PADDING);
+
+    let width = PADDING + swatch_width + text_width + PADDING;
+    let height = PADDING + text_height + PADDING;
+
+    let below = (anchor.y + anchor.height + GAP) as i64;
+    let above = anchor.y as i64 - (GAP + height) as i64;
+
+    let y = if below + height as i64 <= framebuffer.height() as i64 {
+        below
+    } else {
+        above.max(0)
+    };
+    let x = (anchor.x as i64)
+        .min(framebuffer.width() as i64 - width as i64)
+        .max(0);
+
+    let foreground = framebuffer::contrasting(background);
+
+    framebuffer.fill_rect(x, y, width, height, background);
+
+    let text_x = x + (PADDING + swatch_width) as i64;
+    let text_y = y + PADDING as i64;
+
+    if let Some(color) = swatch {
+        let size = text_height;
+
+        framebuffer.fill_rect(text_x - swatch_width as i64, text_y, size, size, foreground);
+        framebuffer.fill_rect(
+            text_x - swatch_width as i64 + 1,
+            text_y + 1,
+            size - 2,
+            size - 2,
+            color,
+        );
+    }
+
+    for (line_y, line) in (text_y..)
+        .step_by((line_height + LINE_SPACING) as usize)
+        .zip(lines)
+    {
+        framebuffer.draw_text(text_x, line_y, line, SCALE, foreground);
+    }
+}
+
+/// How many pixels wide a cell of the lens has to be before its value is written inside it
+pub const CELL_VALUES_ZOOM: u32 = 64;
+
+/// Writes the color of every cell inside it, as hex or as a line per channel
+pub fn draw_cell_values(framebuffer: &mut Framebuffer, cells: &[LensCell], format: ColorFormat) {
+    for &LensCell {
+        rect: cell,
+        color,
+        shown,
+    } in cells
+    {
+        let (r, g, b) = framebuffer::to_rgb(color);
+
+        let lines = match format {
+            ColorFormat::Hex => vec![format!("{r:02X}{g:02X}{b:02X}")],
+            ColorFormat::Rgb => vec![format!("R{r:>4}"), format!("G{g:>4}"), format!("B{b:>4}")],
+        };
+
+        let text_width = lines
+            .iter()
+            .map(|line| font::text_width(line, 1))
+            .max()
+            .unwrap_or(0);
+        let text_height = lines.len() as u32 * (font::text_height(1) + LINE_SPACING) - LINE_SPACING;
+
+        // As big as still leaves some of the color around the text
+        let scale = (cell.width.saturating_sub(2 * PADDING) / text_width)
+            .min(cell.height.saturating_sub(2 * PADDING) / text_height)
+            .min(SCALE);
+
+        if scale == 0 {
+            continue;
+        }
+
+        let line_height = font::text_height(scale) + LINE_SPACING * scale;
+        let x = cell.x + (cell.width - text_width * scale) / 2;
+        let y = cell.y + (cell.height - text_height * scale) / 2;
+
+        for (line_y, line) in (y..).step_by(line_height as usize).zip(&lines) {
+            framebuffer.draw_text(
+                x.into(),
+                line_y.into(),
+                line,
+                scale,
+                framebuffer::contrasting(shown),
+            );
+        }
+    }
+}
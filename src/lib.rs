@@ -0,0 +1,21 @@
+#![warn(clippy::all, clippy::nursery)]
+
+pub mod args;
+pub mod contrast;
+pub mod cvd;
+pub mod delta_e;
+pub mod font;
+pub mod framebuffer;
+pub mod gradient;
+pub mod hud;
+pub mod measure;
+pub mod picker_context;
+pub mod picker_event_loop;
+pub mod pin;
+pub mod sample;
+pub mod screenshots;
+pub mod spec_sheet;
+pub mod srgb;
+pub mod text_color;
+pub mod timings;
+pub mod wand;
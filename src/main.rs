@@ -1,6 +1,5 @@
 #![warn(clippy::all, clippy::nursery)]
 
-use args::{Args, ColorFormat};
 use clap::Parser;
 use color_eyre::eyre::Result;
 use copypasta_ext::display::DisplayServer;
@@ -8,42 +7,87 @@ use image::{Pixel, Rgb};
 use std::io::{Write, stdout, IsTerminal};
 use termcolor::{Color, ColorChoice, ColorSpec, StandardStream, WriteColor};
 
-pub mod args;
-pub mod picker_context;
-pub mod picker_event_loop;
-pub mod screenshots;
-
-use picker_event_loop::launch_picker_gui;
+use crabpicker::args::Args;
+use crabpicker::contrast::{self, Level};
+use crabpicker::picker_context::Pick;
+use crabpicker::picker_event_loop::{launch_picker_gui, Output};
 
 fn main() -> Result<()> {
     color_eyre::install()?;
 
     let args = Args::parse();
 
-    let (r, g, b) = match launch_picker_gui(&args)? {
-        Some(rgb) => rgb,
+    let output = match launch_picker_gui(&args)? {
+        Some(output) => output,
         None => {
             println!("Picker was cancelled");
             return Ok(());
         }
     };
 
-    let formatted_rgb = match args.format {
-        ColorFormat::Hex => format!("#{r:02X}{g:02X}{b:02X}"),
-        ColorFormat::Rgb => format!("{r}, {g}, {b}"),
+    let clipboard_contents = match output {
+        Output::Color(pick) => print_pick(&args, "", pick),
+        Output::Contrast {
+            foreground,
+            background,
+        } => {
+            let formatted_rgb = print_pick(&args, "Foreground: ", foreground);
+            print_pick(&args, "Background: ", background);
+            print_contrast(&args, foreground.color, background.color);
+
+            formatted_rgb
+        }
+        Output::Text(text_colors) => {
+            let formatted_rgb = args.format.format(text_colors.foreground);
+
+            print_color_line(text_colors.foreground, &format!("Text: {formatted_rgb}"));
+            print_color_line(
+                text_colors.background,
+                &format!("Background: {}", args.format.format(text_colors.background)),
+            );
+            println!("Confidence: {:.0}%", text_colors.confidence * 100.0);
+
+            formatted_rgb
+        }
+        Output::Gradient(gradient) => {
+            let formatted_gradient = if args.gradient_list {
+                gradient
+                    .colors
+                    .iter()
+                    .map(|&rgb| args.format.format(rgb))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            } else {
+                gradient.css()
+            };
+            println!("{formatted_gradient}");
+
+            formatted_gradient
+        }
+        Output::Region(region) => {
+            let formatted_rgb = args.format.format(region.color);
+
+            print_color_line(region.color, &format!("Average: {formatted_rgb}"));
+            println!("{region}");
+
+            formatted_rgb
+        }
+        Output::Measurement(measurement) => {
+            let formatted_measurement = measurement.to_string();
+            println!("{formatted_measurement}");
+
+            formatted_measurement
+        }
     };
 
     if !stdout().is_terminal() {
-        println!("{formatted_rgb}");
         return Ok(());
     }
 
-    print_color_result((r, g, b), &formatted_rgb);
-
     if !args.disable_clipboard {
         let clip_res = DisplayServer::select()
             .try_context()
-            .map(|mut x| x.set_contents(formatted_rgb))
+            .map(|mut x| x.set_contents(clipboard_contents))
             .expect("Could not find display server");
 
         if let Err(err) = clip_res {
@@ -54,6 +98,66 @@ fn main() -> Result<()> {
     Ok(())
 }
 
+/// Prints a picked color and how it looked while simulating a deficiency, returns the color as
+/// formatted for the clipboard
+fn print_pick(args: &Args, label: &str, pick: Pick) -> String {
+    let formatted_rgb = args.format.format(pick.color);
+
+    print_color_line(pick.color, &format!("{label}{formatted_rgb}"));
+
+    if let Some((deficiency, rgb)) = pick.simulated {
+        let formatted_simulated = args.format.format(rgb);
+        print_color_line(rgb, &format!("{label}{deficiency}: {formatted_simulated}"));
+    }
+
+    formatted_rgb
+}
+
+fn print_contrast(args: &Args, foreground: (u8, u8, u8), background: (u8, u8, u8)) {
+    let ratio = contrast::contrast_ratio(foreground, background);
+
+    println!("Contrast ratio: {ratio:.2}:1");
+
+    for level in [Level::AA, Level::AAA] {
+        for (text, needed) in [
+            ("normal text", level.normal_text()),
+            ("large text", level.large_text()),
+        ] {
+            let result = if ratio >= needed { "pass" } else { "fail" };
+            println!("  {level} {text}: {result}");
+        }
+    }
+
+    println!(
+        "APCA Lc: {:.1}",
+        contrast::apca_contrast(foreground, background)
+    );
+
+    for level in [Level::AA, Level::AAA] {
+        if ratio >= level.normal_text() {
+            continue;
+        }
+
+        match contrast::nearest_passing(foreground, background, level) {
+            Some(rgb) => print_color_line(
+                rgb,
+                &format!(
+                    "Nearest foreground passing {level}: {}",
+                    args.format.format(rgb)
+                ),
+            ),
+            None => println!("No foreground passes {level} on this background"),
+        }
+    }
+}
+
+/// Prints a line with `rgb` as its background, unless the output is not a terminal
+fn print_color_line(rgb: (u8, u8, u8), text: &str) {
+    if !stdout().is_terminal() || print_color_result(rgb, text).is_none() {
+        println!("{text}");
+    }
+}
+
 fn print_color_result((r, g, b): (u8, u8, u8), rgb_hex: &str) -> Option<()> {
     let background = 255 - Rgb([r, g, b]).to_luma().0[0];
 
@@ -70,7 +174,7 @@ fn print_color_result((r, g, b): (u8, u8, u8), rgb_hex: &str) -> Option<()> {
 
     stdout.reset().ok()?;
 
-    stdout.write_all(&[b'\n']).ok()?;
+    stdout.write_all(b"\n").ok()?;
 
     Some(())
 }
@@ -0,0 +1,65 @@
+use std::fmt::{self, Display};
+
+use winit::dpi::PhysicalPosition;
+
+use crate::framebuffer::to_rgb;
+
+/// The overlay color of the measuring line and its anchor
+pub const MEASURE_COLOR: u32 = 0xFF00FF;
+
+/// How far the measured point is pulled towards an edge while snapping
+pub const SNAP_RADIUS: i32 = 8;
+
+/// A distance between two global desktop positions
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Measurement {
+    pub from: PhysicalPosition<i32>,
+    pub to: PhysicalPosition<i32>,
+}
+
+impl Measurement {
+    pub const fn dx(&self) -> i32 {
+        self.to.x - self.from.x
+    }
+
+    pub const fn dy(&self) -> i32 {
+        self.to.y - self.from.y
+    }
+
+    pub fn distance(&self) -> f64 {
+        (self.dx() as f64).hypot(self.dy() as f64)
+    }
+
+    /// The lines shown next to the cursor while measuring
+    pub fn lines(&self) -> [String; 2] {
+        [
+            format!("dx: {} dy: {}", self.dx(), self.dy()),
+            format!("d: {:.2}", self.distance()),
+        ]
+    }
+}
+
+impl Display for Measurement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "dx: {} dy: {} distance: {:.2} from ({}, {}) to ({}, {})",
+            self.dx(),
+            self.dy(),
+            self.distance(),
+            self.from.x,
+            self.from.y,
+            self.to.x,
+            self.to.y
+        )
+    }
+}
+
+/// How different two 0RGB pixels are, as the largest difference of any channel
+pub fn color_difference(a: u32, b: u32) -> u8 {
+    let (a, b) = (to_rgb(a), to_rgb(b));
+
+    a.0.abs_diff(b.0)
+        .max(a.1.abs_diff(b.1))
+        .max(a.2.abs_diff(b.2))
+}
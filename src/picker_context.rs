@@ -1,8 +1,14 @@
-use std::{cmp::Ordering, collections::HashMap};
+use std::{
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
+    num::NonZeroU32,
+    path::Path,
+    thread::JoinHandle,
+    time::{Duration, Instant},
+};
 
 use color_eyre::eyre::{eyre, Result};
-use image::{imageops, DynamicImage, GenericImage, GenericImageView, Pixel, Rgba};
-use softbuffer::{GraphicsContext, SoftBufferError};
+use softbuffer::{Context, SoftBufferError, Surface};
 use winit::{
     dpi::PhysicalPosition,
     event_loop::EventLoop,
@@ -10,13 +16,99 @@ use winit::{
     window::{Fullscreen, Window, WindowBuilder, WindowId, WindowLevel},
 };
 
-use crate::args::Args;
+use crate::args::{Args, ColorFormat, SampleMode, TableFormat};
+use crate::contrast;
+use crate::cvd::Deficiency;
+use crate::framebuffer::Rect;
+use crate::framebuffer::{self, Framebuffer, LensStyle, LensView};
+use crate::gradient::{self, Gradient};
+use crate::hud;
+use crate::measure::{self, Measurement};
+use crate::pin::{self, Pin, PinnedLens};
+use crate::sample;
 use crate::screenshots::screenshots_ordered;
+#[cfg(feature = "x11")]
+use crate::screenshots::XDisplay;
+use crate::spec_sheet;
+use crate::text_color::{self, TextColors};
+use crate::timings::Timings;
+use crate::wand::{self, Region};
+
+/// How long the hovered color has to stay the same before every monitor highlights it, as that
+/// filters all of them again
+const HIGHLIGHT_DELAY: Duration = Duration::from_millis(150);
+
+/// A picked color, with how it looked while the screen was simulating a color vision deficiency
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Pick {
+    pub color: (u8, u8, u8),
+    pub simulated: Option<(Deficiency, (u8, u8, u8))>,
+}
 
 pub struct PickerContext {
     windows: Vec<Window>,
-    graphics: HashMap<WindowId, (GraphicsContext, DynamicImage, SoftBufferImage)>,
+    graphics: HashMap<WindowId, (Surface, Framebuffer)>,
+    /// Where each window's monitor starts in global desktop coordinates, in the order of the
+    /// monitors so that overlapping ones always resolve the same way
+    origins: Vec<(WindowId, PhysicalPosition<i32>)>,
+    /// Used to move the pointer when winit cannot
+    #[cfg(feature = "x11")]
+    xtest: Option<XDisplay>,
     cursor: bool,
+    /// The window the lens, or the label that follows the cursor without it, is currently drawn on
+    lens_window: Option<WindowId>,
+    /// Where the lens itself was last drawn, if it is still shown
+    lens_rect: Option<(WindowId, Rect)>,
+    /// Windows to redraw once all pending events are handled
+    pending_draws: HashSet<WindowId>,
+    format: ColorFormat,
+    /// The size of the square around the cursor a pick combines
+    sample_size: u32,
+    sample_mode: SampleMode,
+    lens_style: LensStyle,
+    /// The deficiency every monitor is currently shown as
+    simulation: Option<Deficiency>,
+    /// Whether pixels that do not match the hovered color are dimmed
+    highlight_mode: bool,
+    /// The color every monitor currently highlights the matches of
+    highlighted: Option<u32>,
+    /// The hovered color to highlight next, with when the cursor moved onto it
+    next_highlight: Option<(u32, Instant)>,
+    /// Whether the filter has changed since every monitor was last filtered
+    filter_outdated: bool,
+    /// How much any channel may differ from the hovered color to still match it
+    match_tolerance: u8,
+    pub timings: Timings,
+    pub hud: bool,
+    /// The first pick of `--contrast`, which is compared against the hovered color
+    pub foreground: Option<Pick>,
+    /// Where measuring starts from
+    anchor: Option<PhysicalPosition<i32>>,
+    /// Whether the measured point snaps to the nearest color edge
+    pub snap_edges: bool,
+    /// How much any channel has to change between two pixels for there to be an edge
+    edge_threshold: u8,
+    /// Whether picks estimate the colors of the text under the cursor
+    pub text_mode: bool,
+    /// Whether picks sample a gradient along a line instead
+    pub gradient_mode: bool,
+    /// Where the line of the gradient starts
+    drag_start: Option<PhysicalPosition<i32>>,
+    /// The gradient to where the cursor last was, with how many stops it has
+    gradient: Option<(Gradient, usize)>,
+    /// The measuring and gradient lines as they were last drawn, to know which windows have to
+    /// be drawn again when they move
+    segments: Vec<Segment>,
+    /// Whether picks flood fill the area around them instead
+    pub wand_mode: bool,
+    /// How much any channel may differ from the clicked pixel to be filled
+    wand_tolerance: u8,
+    /// The last filled area, with the window it is on
+    region: Option<(WindowId, Region)>,
+    /// Picks left on the screen, the cursor is compared against the last one
+    pins: Vec<Pin>,
+    /// Spec sheets that are still being written
+    exports: Vec<JoinHandle<()>>,
     pub toggle_zoom: bool,
     pub hold_zoom: bool,
     pub hold_right_click: bool,
@@ -24,55 +116,122 @@ pub struct PickerContext {
     pub zoom_size: u32,
 }
 
-type SoftBufferImage = Vec<u32>;
-
 impl PickerContext {
     pub fn new(event_loop: &EventLoop<()>, args: &Args) -> Result<Self> {
-        let monitors = event_loop.available_monitors().collect::<Vec<_>>();
+        let timings = Timings::new(args.timings);
 
-        let images = screenshots_ordered(&monitors)?;
+        let monitors = timings.time("list monitors", || {
+            event_loop.available_monitors().collect::<Vec<_>>()
+        });
+
+        let framebuffers = timings.time("capture monitors", || {
+            screenshots_ordered(&monitors, &timings)
+        })?;
 
         let cursor = args.size >= 5;
 
-        let windows = monitors
-            .into_iter()
-            .map(|monitor| {
-                let mut builder = WindowBuilder::new()
-                    .with_decorations(false)
-                    .with_window_level(WindowLevel::AlwaysOnTop)
-                    .with_resizable(false)
-                    .with_maximized(true);
-
-                if args.exclusive {
-                    let video_mode = get_ideal_video_mode(monitor).unwrap();
-                    builder = builder.with_fullscreen(Some(Fullscreen::Exclusive(video_mode)));
-                } else {
-                    builder = builder.with_fullscreen(Some(Fullscreen::Borderless(Some(monitor))));
-                }
+        let sample_size = args
+            .sample_radius
+            .map_or(args.sample, |radius| 2 * radius + 1);
 
-                let built = builder.build(event_loop)?;
+        let origins = monitors.iter().map(|x| x.position()).collect::<Vec<_>>();
 
-                built.set_cursor_icon(winit::window::CursorIcon::Crosshair);
-                built.set_cursor_visible(cursor);
-                Ok(built)
-            })
-            .collect::<Result<Vec<_>>>()?;
+        let windows = timings.time("create windows", || {
+            monitors
+                .into_iter()
+                .map(|monitor| {
+                    let mut builder = WindowBuilder::new()
+                        .with_decorations(false)
+                        .with_window_level(WindowLevel::AlwaysOnTop)
+                        .with_resizable(false)
+                        .with_maximized(true);
 
-        let graphics = windows
-            .iter()
-            .zip(images)
-            .map(|(window, image)| {
-                let ctx = unsafe { GraphicsContext::new(&window, &window) }?;
-                let cached = image_to_softbuffer(&image);
-                Ok((window.id(), (ctx, image, cached)))
+                    if args.exclusive {
+                        let video_mode = get_ideal_video_mode(monitor).unwrap();
+                        builder = builder.with_fullscreen(Some(Fullscreen::Exclusive(video_mode)));
+                    } else {
+                        builder =
+                            builder.with_fullscreen(Some(Fullscreen::Borderless(Some(monitor))));
+                    }
+
+                    let built = builder.build(event_loop)?;
+
+                    built.set_cursor_icon(winit::window::CursorIcon::Crosshair);
+                    built.set_cursor_visible(cursor);
+                    Ok(built)
+                })
+                .collect::<Result<Vec<_>>>()
+        })?;
+
+        let graphics = timings
+            .time("create graphics contexts", || {
+                let context = unsafe { Context::new(event_loop) }?;
+
+                windows
+                    .iter()
+                    .zip(framebuffers)
+                    .map(|(window, framebuffer)| {
+                        let mut surface = unsafe { Surface::new(&context, window) }?;
+
+                        if let (Some(width), Some(height)) = (
+                            NonZeroU32::new(framebuffer.width()),
+                            NonZeroU32::new(framebuffer.height()),
+                        ) {
+                            surface.resize(width, height)?;
+                        }
+
+                        Ok((window.id(), (surface, framebuffer)))
+                    })
+                    .collect::<Result<_, SoftBufferError>>()
             })
-            .collect::<Result<_, SoftBufferError>>()
             .map_err(|err| eyre!("Could not create graphics context: {err}"))?;
 
+        let origins = windows.iter().map(Window::id).zip(origins).collect();
+
         Ok(Self {
             windows,
             graphics,
+            origins,
+            #[cfg(feature = "x11")]
+            xtest: XDisplay::open(),
             cursor,
+            lens_window: None,
+            lens_rect: None,
+            pending_draws: HashSet::new(),
+            format: args.format,
+            sample_size,
+            sample_mode: args.sample_mode,
+            lens_style: LensStyle {
+                view: LensView::Normal,
+                grid: !args.disable_grid,
+                grid_color: args.grid_color,
+                circle: args.circle,
+                shadow: args.shadow,
+                offset: args.offset,
+                sample_radius: sample_size / 2,
+            },
+            simulation: None,
+            highlight_mode: false,
+            highlighted: None,
+            next_highlight: None,
+            filter_outdated: false,
+            match_tolerance: args.match_tolerance,
+            timings,
+            hud: !args.disable_hud,
+            foreground: None,
+            anchor: None,
+            snap_edges: false,
+            edge_threshold: args.edge_threshold,
+            text_mode: false,
+            gradient_mode: false,
+            drag_start: None,
+            gradient: None,
+            segments: Vec::new(),
+            wand_mode: false,
+            wand_tolerance: args.wand_tolerance,
+            region: None,
+            pins: Vec::new(),
+            exports: Vec::new(),
             toggle_zoom: false,
             hold_zoom: args.zoom,
             hold_right_click: false,
@@ -100,107 +259,896 @@ impl PickerContext {
             .for_each(|x| x.set_cursor_visible(cursor))
     }
 
-    pub fn get_pixel(
+    /// Converts a position inside of a window into global desktop coordinates
+    pub fn to_global(
         &self,
-        window_id: &WindowId,
-        position: PhysicalPosition<u32>,
-    ) -> Option<(u8, u8, u8)> {
-        let (_, image, _) = self.graphics.get(window_id)?;
-        let pixel = image.get_pixel(position.x, position.y).0;
+        window_id: WindowId,
+        position: PhysicalPosition<f64>,
+    ) -> Option<PhysicalPosition<i32>> {
+        let (_, origin) = self.origins.iter().find(|(id, _)| *id == window_id)?;
+        let position = position.cast::<i32>();
 
-        Some((pixel[0], pixel[1], pixel[2]))
+        Some(PhysicalPosition::new(
+            origin.x + position.x,
+            origin.y + position.y,
+        ))
     }
 
-    pub fn draw_empty_window(&mut self, window_id: WindowId) {
-        let (graphics_ctx, ref image, ref cached) = self.graphics.get_mut(&window_id).unwrap();
+    /// Where the monitor of a window starts in global desktop coordinates
+    fn origin(&self, window_id: WindowId) -> PhysicalPosition<i32> {
+        self.origins
+            .iter()
+            .find_map(|&(id, origin)| (id == window_id).then_some(origin))
+            .expect("every window has an origin")
+    }
+
+    /// Finds the window showing a global desktop position and the position inside of it
+    pub fn locate(
+        &self,
+        position: PhysicalPosition<i32>,
+    ) -> Option<(WindowId, PhysicalPosition<u32>)> {
+        self.origins.iter().find_map(|(window_id, origin)| {
+            let (_, framebuffer) = self.graphics.get(window_id)?;
+
+            let x = u32::try_from(position.x - origin.x).ok()?;
+            let y = u32::try_from(position.y - origin.y).ok()?;
 
-        graphics_ctx.set_buffer(cached, image.width() as u16, image.height() as u16);
+            (x < framebuffer.width() && y < framebuffer.height())
+                .then_some((*window_id, PhysicalPosition::new(x, y)))
+        })
     }
 
-    pub fn redraw_window(
-        &mut self,
-        window_id: WindowId,
-        mouse_pos: PhysicalPosition<u32>,
-    ) -> Option<()> {
-        let (graphics_ctx, ref image, _) = self.graphics.get_mut(&window_id).unwrap();
+    /// Moves a global position by the given amount, carrying over into neighbouring monitors.
+    ///
+    /// If there is no monitor to carry over into, the position stops at the edge of its own.
+    pub fn step(&self, position: PhysicalPosition<i32>, dx: i32, dy: i32) -> PhysicalPosition<i32> {
+        let moved =
+            PhysicalPosition::new(position.x.saturating_add(dx), position.y.saturating_add(dy));
 
-        let square_halfway: u32 = self.zoom_size / 2;
+        if self.locate(moved).is_some() {
+            return moved;
+        }
 
-        let zoom_start_in_bounds = image.in_bounds(
-            mouse_pos.x.checked_sub(square_halfway)?,
-            mouse_pos.y.checked_sub(square_halfway)?,
-        );
+        let Some((window_id, _)) = self.locate(position) else {
+            return position;
+        };
+
+        let origin = self.origin(window_id);
+        let (_, framebuffer) = &self.graphics[&window_id];
+
+        PhysicalPosition::new(
+            moved
+                .x
+                .clamp(origin.x, origin.x + framebuffer.width() as i32 - 1),
+            moved
+                .y
+                .clamp(origin.y, origin.y + framebuffer.height() as i32 - 1),
+        )
+    }
+
+    /// Moves a global position in the given direction until the color differs from where it
+    /// started by more than the edge threshold, stopping at the edge of the desktop
+    pub fn next_edge(
+        &self,
+        position: PhysicalPosition<i32>,
+        dx: i32,
+        dy: i32,
+    ) -> PhysicalPosition<i32> {
+        let Some(start) = self.capture_pixel(position) else {
+            return position;
+        };
+
+        let mut current = position;
 
-        let zoom_end_in_bounds =
-            image.in_bounds(mouse_pos.x + square_halfway, mouse_pos.y + square_halfway);
+        loop {
+            let next = self.step(current, dx, dy);
 
-        if !zoom_start_in_bounds | !zoom_end_in_bounds {
-            return None;
+            if next == current {
+                return current;
+            }
+
+            current = next;
+
+            if self.is_edge(start, current) {
+                return current;
+            }
         }
+    }
 
-        let mut image = image.clone();
+    fn is_edge(&self, color: u32, position: PhysicalPosition<i32>) -> bool {
+        self.capture_pixel(position)
+            .is_some_and(|other| measure::color_difference(color, other) > self.edge_threshold)
+    }
 
-        // An additional line for the final x and y grid lines
-        let zoomed_size: u32 = self.zoom_size * self.zoom + 1;
-        let zoom_halfway: u32 = zoomed_size / 2;
+    /// Moves the OS cursor to a global desktop position, returns `false` if that is not supported
+    pub fn warp_cursor(&self, position: PhysicalPosition<i32>) -> bool {
+        let Some((window_id, local)) = self.locate(position) else {
+            return false;
+        };
 
-        let cropped_image = image.crop_imm(
-            mouse_pos.x - square_halfway,
-            mouse_pos.y - square_halfway,
-            self.zoom_size,
-            self.zoom_size,
-        );
+        let window = self.windows.iter().find(|x| x.id() == window_id).unwrap();
 
-        let mut total_light_value = 0;
+        if window.set_cursor_position(local).is_ok() {
+            return true;
+        }
+
+        #[cfg(feature = "x11")]
+        if let Some(display) = &self.xtest {
+            return display.warp_pointer(position.x, position.y);
+        }
+
+        false
+    }
 
-        for (_, _, pixel) in cropped_image.pixels() {
-            let grayscale = pixel.to_luma();
+    /// A pixel of the capture at a global desktop position
+    fn capture_pixel(&self, position: PhysicalPosition<i32>) -> Option<u32> {
+        let (window_id, position) = self.locate(position)?;
+        let (_, framebuffer) = self.graphics.get(&window_id)?;
 
-            total_light_value += grayscale.0[0] as u32;
+        framebuffer.capture_pixel(position.x.into(), position.y.into())
+    }
+
+    /// The captured pixels of the sampled square around a global desktop position, from
+    /// whichever framebuffer they are on
+    fn sample(&self, position: PhysicalPosition<i32>) -> Vec<u32> {
+        let radius = (self.sample_size / 2) as i32;
+
+        (-radius..=radius)
+            .flat_map(|dy| (-radius..=radius).map(move |dx| (dx, dy)))
+            .filter_map(|(dx, dy)| {
+                self.capture_pixel(PhysicalPosition::new(position.x + dx, position.y + dy))
+            })
+            .collect()
+    }
+
+    pub fn get_pixel(&self, position: PhysicalPosition<i32>) -> Option<Pick> {
+        // Only the cursor itself has to be on a monitor, the rest of the sample may not be
+        self.locate(position)?;
+
+        let pixels = self.sample(position);
+        let pixel = sample::combine(self.sample_mode, &pixels)?;
+
+        // Simulated from the capture, as what is shown can also be dimmed by highlighting
+        let simulated = self.simulation.and_then(|deficiency| {
+            let shown = pixels
+                .iter()
+                .map(|&pixel| deficiency.simulate(pixel))
+                .collect::<Vec<_>>();
+
+            Some((
+                deficiency,
+                framebuffer::to_rgb(sample::combine(self.sample_mode, &shown)?),
+            ))
+        });
+
+        Some(Pick {
+            color: framebuffer::to_rgb(pixel),
+            simulated,
+        })
+    }
+
+    /// Starts the line of a gradient at `position`
+    pub fn start_drag(&mut self, position: Option<PhysicalPosition<i32>>) {
+        self.drag_start = position;
+        self.gradient = None;
+        self.request_all_draws();
+    }
+
+    /// The gradient from where dragging started to `position`, if it has started and moved
+    pub fn gradient(&self, position: Option<PhysicalPosition<i32>>) -> Option<Gradient> {
+        self.gradient
+            .as_ref()
+            .filter(|(gradient, _)| Some(gradient.to) == position)
+            .map(|(gradient, _)| gradient.clone())
+    }
+
+    /// Samples the gradient again if the cursor moved away from where it was last sampled to
+    fn update_gradient(&mut self, position: Option<PhysicalPosition<i32>>) {
+        let sampled_to = self.gradient.as_ref().map(|(gradient, _)| gradient.to);
+
+        if sampled_to.is_some() && sampled_to == position {
+            return;
         }
 
-        let average_light_value = total_light_value / (self.zoom_size.pow(2));
-        let border_color = (255 - average_light_value) as u8;
+        self.gradient = self
+            .drag_start
+            .zip(position)
+            .filter(|(from, to)| from != to)
+            .map(|(from, to)| {
+                let colors = gradient::line_positions(from, to)
+                    .filter_map(|position| self.get_pixel(position))
+                    .map(|pick| pick.color)
+                    .collect();
 
-        let mut zoomed_in_image =
-            cropped_image.resize(zoomed_size, zoomed_size, imageops::FilterType::Nearest);
+                let gradient = Gradient { from, to, colors };
+                let stops = gradient.stops().len();
+
+                (gradient, stops)
+            });
+    }
 
-        let zoom = self.zoom as usize;
-        let border_color = Rgba([border_color, border_color, border_color, 255]);
+    /// Flood fills the capture from `position` and highlights the filled area, or removes the
+    /// highlight for `None`
+    pub fn fill(&mut self, position: Option<PhysicalPosition<i32>>) {
+        self.region = position
+            .and_then(|position| self.locate(position))
+            .and_then(|(window_id, local)| {
+                let (_, framebuffer) = self.graphics.get(&window_id)?;
+                let region = wand::flood_fill(
+                    framebuffer,
+                    self.origin(window_id),
+                    local,
+                    self.wand_tolerance,
+                )?;
 
-        for new_line in (0..zoomed_size).step_by(zoom) {
-            for line_length in 0..zoomed_size {
-                zoomed_in_image.put_pixel(new_line, line_length, border_color);
-                zoomed_in_image.put_pixel(line_length, new_line, border_color);
+                Some((window_id, region))
+            });
+
+        self.request_all_draws();
+    }
+
+    /// The filled area, if `position` is inside of it
+    pub fn region_at(&self, position: Option<PhysicalPosition<i32>>) -> Option<&Region> {
+        let (_, region) = self.region.as_ref()?;
+
+        position
+            .is_some_and(|position| region.contains(position))
+            .then_some(region)
+    }
+
+    /// Pins the pick at `position`, along with the lens if it is shown
+    pub fn pin(&mut self, position: Option<PhysicalPosition<i32>>) {
+        let Some((position, pick)) = position.and_then(|pos| Some((pos, self.get_pixel(pos)?)))
+        else {
+            return;
+        };
+
+        let lens = self
+            .should_display_zoom()
+            .then(|| {
+                let (window_id, local) = self.locate(position)?;
+                let (_, framebuffer) = &self.graphics[&window_id];
+
+                Some(PinnedLens {
+                    zoom: self.zoom,
+                    zoom_size: self.zoom_size,
+                    pixels: framebuffer.lens_pixels(local.into(), self.zoom_size),
+                })
+            })
+            .flatten();
+
+        self.pins.push(Pin {
+            position,
+            pick,
+            lens,
+        });
+
+        self.request_all_draws();
+    }
+
+    /// Removes the last pin
+    pub fn unpin(&mut self) {
+        if self.pins.pop().is_some() {
+            self.request_all_draws();
+        }
+    }
+
+    /// Where the desktop starts and how big it is, going around every monitor
+    fn desktop_bounds(&self) -> (PhysicalPosition<i32>, u32, u32) {
+        let bounds = self.origins.iter().map(|(window_id, origin)| {
+            let (_, framebuffer) = &self.graphics[window_id];
+            let size = (framebuffer.width() as i32, framebuffer.height() as i32);
+
+            (origin.x, origin.y, origin.x + size.0, origin.y + size.1)
+        });
+
+        let (left, top, right, bottom) = bounds
+            .reduce(|a, b| (a.0.min(b.0), a.1.min(b.1), a.2.max(b.2), a.3.max(b.3)))
+            .unwrap_or_default();
+
+        (
+            PhysicalPosition::new(left, top),
+            (right - left) as u32,
+            (bottom - top) as u32,
+        )
+    }
+
+    /// The capture of a `width` by `height` area of the desktop starting at `origin`, put together
+    /// from every monitor it covers and black where there is none
+    fn capture_area(&self, origin: PhysicalPosition<i32>, width: u32, height: u32) -> Framebuffer {
+        let mut area = Framebuffer::new(width, height, vec![0; width as usize * height as usize]);
+
+        for (window_id, monitor) in &self.origins {
+            let (_, framebuffer) = &self.graphics[window_id];
+
+            let left = origin.x.max(monitor.x);
+            let top = origin.y.max(monitor.y);
+            let right = (origin.x + width as i32).min(monitor.x + framebuffer.width() as i32);
+            let bottom = (origin.y + height as i32).min(monitor.y + framebuffer.height() as i32);
+
+            if left >= right || top >= bottom {
+                continue;
             }
+
+            let overlap = Rect {
+                x: (left - monitor.x) as u32,
+                y: (top - monitor.y) as u32,
+                width: (right - left) as u32,
+                height: (bottom - top) as u32,
+            };
+
+            framebuffer.copy_capture(
+                overlap,
+                &mut area,
+                (left - origin.x) as u32,
+                (top - origin.y) as u32,
+            );
         }
 
-        imageops::replace(
-            &mut image,
-            &zoomed_in_image,
-            (mouse_pos.x as i64) - zoom_halfway as i64,
-            (mouse_pos.y as i64) - zoom_halfway as i64,
+        area
+    }
+
+    /// Writes the desktop with the pins marked on it to `path`, with a table of them next to it.
+    ///
+    /// With `crop` only the area around the pins is copied out of the captures.
+    pub fn export_spec_sheet(&mut self, path: &Path, table: TableFormat, crop: bool) -> bool {
+        if self.pins.is_empty() {
+            return false;
+        }
+
+        let (mut origin, mut width, mut height) = self.desktop_bounds();
+
+        if let Some(area) = crop
+            .then(|| spec_sheet::crop_area(origin, width, height, &self.pins))
+            .flatten()
+        {
+            origin = PhysicalPosition::new(origin.x + area.x as i32, origin.y + area.y as i32);
+            (width, height) = (area.width, area.height);
+        }
+
+        let desktop = self.capture_area(origin, width, height);
+
+        let export = spec_sheet::export(
+            path.to_owned(),
+            desktop,
+            origin,
+            &self.pins,
+            self.format,
+            table,
         );
+        self.exports.push(export);
 
-        let buffer = image_to_softbuffer(&image);
+        true
+    }
 
-        graphics_ctx.set_buffer(&buffer, image.width() as u16, image.height() as u16);
+    /// Waits for every spec sheet that is still being written
+    pub fn finish_exports(&mut self) {
+        for export in self.exports.drain(..) {
+            if export.join().is_err() {
+                eprintln!("Exporting a spec sheet crashed");
+            }
+        }
+    }
 
-        Some(())
+    /// Writes the area between the anchor and `position` to `path`, or the whole capture of the
+    /// monitor under the cursor without an anchor.
+    ///
+    /// Returns `false` if there is nothing to save.
+    pub fn save_capture(
+        &self,
+        position: Option<PhysicalPosition<i32>>,
+        path: &Path,
+    ) -> Result<bool> {
+        let image = match self.measurement(position) {
+            Some(measurement) => {
+                let (from, to) = (measurement.from, measurement.to);
+
+                self.capture_area(
+                    PhysicalPosition::new(from.x.min(to.x), from.y.min(to.y)),
+                    from.x.abs_diff(to.x) + 1,
+                    from.y.abs_diff(to.y) + 1,
+                )
+            }
+            None => {
+                let Some((window_id, _)) = position.and_then(|pos| self.locate(pos)) else {
+                    return Ok(false);
+                };
+                let (_, framebuffer) = &self.graphics[&window_id];
+
+                framebuffer.capture_crop(Rect {
+                    x: 0,
+                    y: 0,
+                    width: framebuffer.width(),
+                    height: framebuffer.height(),
+                })
+            }
+        };
+
+        image
+            .to_image()
+            .save(path)
+            .map_err(|err| eyre!("Could not save {}: {err}", path.display()))?;
+
+        Ok(true)
     }
 
-    pub fn request_draw_all(&self) {
-        self.windows
-            .iter()
-            .for_each(|window| window.request_redraw());
+    /// Writes the lens as it is shown to `path`, returns `false` if it is not shown
+    pub fn save_lens(&self, path: &Path) -> Result<bool> {
+        let Some((window_id, rect)) = self.lens_rect else {
+            return Ok(false);
+        };
+        let (_, framebuffer) = &self.graphics[&window_id];
+
+        framebuffer
+            .crop(rect)
+            .to_image()
+            .save(path)
+            .map_err(|err| eyre!("Could not save {}: {err}", path.display()))?;
+
+        Ok(true)
     }
 
-    pub fn request_draw(&self, window_id: WindowId) {
-        self.windows
+    /// Estimates the colors of text from the pixels the lens shows around `position`
+    pub fn text_colors(&self, position: PhysicalPosition<i32>) -> Option<TextColors> {
+        let radius = (self.zoom_size / 2) as i32;
+
+        let pixels = (-radius..=radius)
+            .flat_map(|dy| (-radius..=radius).map(move |dx| (dx, dy)))
+            .filter_map(|(dx, dy)| {
+                self.capture_pixel(PhysicalPosition::new(position.x + dx, position.y + dy))
+            })
+            .collect::<Vec<_>>();
+
+        text_color::estimate(&pixels)
+    }
+
+    /// Shows every monitor as seen with `deficiency`, or normally again if it is already shown
+    pub fn toggle_simulation(&mut self, deficiency: Deficiency) {
+        self.simulation = (self.simulation != Some(deficiency)).then_some(deficiency);
+        self.invalidate_filter();
+    }
+
+    /// Dims every pixel on every monitor that does not match the color at `position`, or stops
+    /// dimming if it already does
+    pub fn toggle_highlight(&mut self, position: Option<PhysicalPosition<i32>>) {
+        self.highlight_mode = !self.highlight_mode;
+
+        if self.highlight_mode {
+            self.update_highlight(position);
+
+            // Nothing is highlighted yet, so there is no point in waiting for the cursor to settle
+            if let Some((color, _)) = self.next_highlight.take() {
+                self.highlighted = Some(color);
+                self.invalidate_filter();
+            }
+        } else {
+            self.highlighted = None;
+            self.next_highlight = None;
+            self.invalidate_filter();
+        }
+    }
+
+    /// Highlights the matches of the color at `position` instead, if highlighting is on.
+    ///
+    /// Filtering every monitor again is far too slow to keep up with the cursor, so the new color
+    /// is only highlighted once the cursor stayed on it for `HIGHLIGHT_DELAY`.
+    pub fn update_highlight(&mut self, position: Option<PhysicalPosition<i32>>) {
+        if !self.highlight_mode {
+            return;
+        }
+
+        let Some(color) = position.and_then(|position| self.capture_pixel(position)) else {
+            return;
+        };
+
+        if self.highlighted == Some(color) {
+            self.next_highlight = None;
+        } else if self.next_highlight.map(|(next, _)| next) != Some(color) {
+            self.next_highlight = Some((color, Instant::now()));
+        }
+    }
+
+    /// When the hovered color will have settled enough to be highlighted, if it is not yet
+    pub fn highlight_deadline(&self) -> Option<Instant> {
+        self.next_highlight
+            .map(|(_, since)| since + HIGHLIGHT_DELAY)
+    }
+
+    /// Schedules every monitor to be filtered again before it is next drawn
+    fn invalidate_filter(&mut self) {
+        self.filter_outdated = true;
+        self.request_all_draws();
+    }
+
+    /// Filters every monitor through the simulated deficiency and the highlighted color
+    fn apply_filter(&mut self) {
+        let (simulation, highlighted, tolerance) =
+            (self.simulation, self.highlighted, self.match_tolerance);
+
+        let filter = (simulation.is_some() || highlighted.is_some()).then_some(move |pixel| {
+            let shown = simulation.map_or(pixel, |deficiency| deficiency.simulate(pixel));
+
+            match highlighted {
+                Some(color) if measure::color_difference(color, pixel) > tolerance => {
+                    framebuffer::dim(shown)
+                }
+                _ => shown,
+            }
+        });
+
+        for (_, framebuffer) in self.graphics.values_mut() {
+            framebuffer.set_filter(filter);
+        }
+
+        self.filter_outdated = false;
+    }
+
+    /// Sets the anchor to measure from at `position`, or removes it if there already is one
+    pub fn toggle_anchor(&mut self, position: Option<PhysicalPosition<i32>>) {
+        self.anchor = match self.anchor {
+            Some(_) => None,
+            None => position.map(|position| self.snapped(position)),
+        };
+
+        self.request_all_draws();
+    }
+
+    /// The measuring and gradient lines that are drawn with the cursor at `position`
+    fn segments(&self, position: Option<PhysicalPosition<i32>>) -> Vec<Segment> {
+        let measurement = self
+            .measurement(position)
+            .map(|measurement| (measurement.from, measurement.to));
+        let gradient = self
+            .gradient
+            .as_ref()
+            .filter(|(gradient, _)| Some(gradient.to) == position)
+            .map(|(gradient, _)| (gradient.from, gradient.to));
+
+        measurement.into_iter().chain(gradient).collect()
+    }
+
+    /// The distance from the anchor to `position`, if there is an anchor
+    pub fn measurement(&self, position: Option<PhysicalPosition<i32>>) -> Option<Measurement> {
+        let (from, to) = self.anchor.zip(position)?;
+
+        Some(Measurement {
+            from,
+            to: self.snapped(to),
+        })
+    }
+
+    fn snapped(&self, position: PhysicalPosition<i32>) -> PhysicalPosition<i32> {
+        if self.snap_edges {
+            self.snap_to_edge(position)
+        } else {
+            position
+        }
+    }
+
+    /// Moves a position onto the nearest color edge within `SNAP_RADIUS` on either axis
+    fn snap_to_edge(&self, position: PhysicalPosition<i32>) -> PhysicalPosition<i32> {
+        let is_edge = |before, after| {
+            self.capture_pixel(before)
+                .is_some_and(|before| self.is_edge(before, after))
+        };
+
+        // An edge between two pixels snaps onto the one after it
+        let nearest = |along: &dyn Fn(i32) -> PhysicalPosition<i32>| {
+            (0..=measure::SNAP_RADIUS)
+                .flat_map(|distance| [distance, -distance])
+                .find(|&offset| is_edge(along(offset - 1), along(offset)))
+                .unwrap_or(0)
+        };
+
+        let dx = nearest(&|offset| PhysicalPosition::new(position.x + offset, position.y));
+        let dy = nearest(&|offset| PhysicalPosition::new(position.x, position.y + offset));
+
+        PhysicalPosition::new(position.x + dx, position.y + dy)
+    }
+
+    /// Draws everything that belongs on a window with the cursor at `position`
+    pub fn draw_window(&mut self, window_id: WindowId, position: Option<PhysicalPosition<i32>>) {
+        let origin = self.origin(window_id);
+        let measurement = self.measurement(position);
+        let gradient = self
+            .gradient
+            .as_ref()
+            .filter(|(gradient, _)| Some(gradient.to) == position);
+        let display_zoom = self.should_display_zoom();
+
+        // What is being measured or dragged, shown next to the cursor
+        let mut lines = Vec::new();
+
+        if let Some(measurement) = measurement {
+            lines.extend(measurement.lines());
+        }
+
+        if let Some((gradient, stops)) = gradient {
+            lines.push(format!("stops: {stops} angle: {:.0}deg", gradient.angle()));
+        }
+
+        if let Some((_, region)) = &self.region {
+            lines.extend(region.lines());
+            lines.push(format!("avg: {}", self.format.format(region.color)));
+        }
+
+        if let Some((pin, pick)) = self
+            .pins
+            .last()
+            .zip(position.and_then(|pos| self.get_pixel(pos)))
+        {
+            lines.extend(pin.comparison(self.pins.len(), pick.color));
+        }
+
+        let label_color = if measurement.is_some() {
+            measure::MEASURE_COLOR
+        } else if gradient.is_some() {
+            gradient::GRADIENT_COLOR
+        } else if self.region.is_some() {
+            wand::REGION_COLOR
+        } else {
+            pin::PIN_COLOR
+        };
+
+        let pins = self
+            .pins
             .iter()
-            .find(|x| x.id() == window_id)
-            .unwrap()
-            .request_redraw();
+            .enumerate()
+            .filter_map(|(index, pin)| {
+                let (pin_window, local) = self.locate(pin.position)?;
+                (pin_window == window_id).then_some((index, local))
+            })
+            .collect::<Vec<_>>();
+
+        let cursor = position
+            .and_then(|pos| self.locate(pos))
+            .filter(|&(cursor_window, _)| cursor_window == window_id)
+            .map(|(_, pos)| pos);
+
+        let (_, framebuffer) = self.graphics.get_mut(&window_id).unwrap();
+
+        framebuffer.clear();
+
+        if let Some((_, region)) = self.region.as_ref().filter(|(id, _)| *id == window_id) {
+            framebuffer.highlight(
+                (region.position.x - origin.x).into(),
+                (region.position.y - origin.y).into(),
+                region.width,
+                &region.mask,
+                wand::REGION_COLOR,
+            );
+        }
+
+        if let Some(measurement) = measurement {
+            draw_segment(
+                framebuffer,
+                origin,
+                (measurement.from, measurement.to),
+                measure::MEASURE_COLOR,
+            );
+        }
+
+        if let Some((gradient, _)) = gradient {
+            draw_segment(
+                framebuffer,
+                origin,
+                (gradient.from, gradient.to),
+                gradient::GRADIENT_COLOR,
+            );
+        }
+
+        for (index, local) in pins {
+            draw_pin(
+                framebuffer,
+                index + 1,
+                &self.pins[index],
+                local,
+                &self.lens_style,
+                self.format,
+            );
+        }
+
+        match cursor {
+            Some(mouse_pos) if display_zoom => {
+                self.draw_lens(window_id, mouse_pos, lines);
+                self.lens_window = Some(window_id);
+            }
+            _ => {
+                if let Some(mouse_pos) = cursor.filter(|_| !lines.is_empty()) {
+                    let anchor = Rect {
+                        x: mouse_pos.x,
+                        y: mouse_pos.y,
+                        width: 16,
+                        height: 16,
+                    };
+
+                    hud::draw_label(framebuffer, anchor, &lines, None, label_color);
+                    self.lens_window = Some(window_id);
+                } else if self.lens_window == Some(window_id) {
+                    self.lens_window = None;
+                }
+
+                if self
+                    .lens_rect
+                    .is_some_and(|(lens_window, _)| lens_window == window_id)
+                {
+                    self.lens_rect = None;
+                }
+            }
+        }
+
+        let (surface, framebuffer) = self.graphics.get_mut(&window_id).unwrap();
+        present(surface, framebuffer);
+    }
+
+    fn draw_lens(
+        &mut self,
+        window_id: WindowId,
+        mouse_pos: PhysicalPosition<u32>,
+        extra_lines: Vec<String>,
+    ) {
+        let origin = self.origin(window_id);
+        let global =
+            PhysicalPosition::new(origin.x + mouse_pos.x as i32, origin.y + mouse_pos.y as i32);
+        let text_colors = self.text_mode.then(|| self.text_colors(global)).flatten();
+        let pick = self.get_pixel(global);
+
+        let (_, framebuffer) = self.graphics.get_mut(&window_id).unwrap();
+
+        let lens = framebuffer.draw_lens(
+            mouse_pos.into(),
+            self.zoom,
+            self.zoom_size,
+            &self.lens_style,
+        );
+
+        self.lens_rect = Some((window_id, lens.rect));
+
+        if self.hud {
+            let color = pick.map_or((0, 0, 0), |pick| pick.color);
+
+            let mut lines = vec![
+                self.format.format(color),
+                format!("x: {} y: {}", global.x, global.y),
+            ];
+
+            if let Some((deficiency, shown)) = pick.and_then(|pick| pick.simulated) {
+                lines.push(format!("{deficiency}: {}", self.format.format(shown)));
+            }
+
+            if let Some(foreground) = self.foreground {
+                let background = color;
+
+                lines.push(format!("fg: {}", self.format.format(foreground.color)));
+                lines.push(format!(
+                    "ratio: {:.2}:1 Lc {:.0}",
+                    contrast::contrast_ratio(foreground.color, background),
+                    contrast::apca_contrast(foreground.color, background)
+                ));
+            }
+
+            lines.extend(extra_lines);
+
+            if let Some(text_colors) = text_colors {
+                lines.push(format!(
+                    "text: {} on {}",
+                    self.format.format(text_colors.foreground),
+                    self.format.format(text_colors.background)
+                ));
+                lines.push(format!(
+                    "confidence: {:.0}%",
+                    text_colors.confidence * 100.0
+                ));
+            }
+
+            if self.lens_style.view != LensView::Normal {
+                lines.push(format!("view: {}", self.lens_style.view.name()));
+            }
+
+            if self.zoom >= hud::CELL_VALUES_ZOOM {
+                hud::draw_cell_values(framebuffer, &lens.cells, self.format);
+            }
+
+            hud::draw_label(
+                framebuffer,
+                lens.rect,
+                &lines,
+                Some(framebuffer::from_rgb(color)),
+                lens.border_color,
+            );
+        }
+    }
+
+    /// Schedules the redraws for the lens to follow `position`, clearing it from the window it was
+    /// last drawn on if that is no longer the one under the cursor
+    pub fn update_lens(&mut self, position: Option<PhysicalPosition<i32>>) {
+        self.update_gradient(position);
+
+        // The measuring and gradient lines can cross any of the monitors, but only the ones
+        // they crossed before or cross now have to be drawn again
+        let segments = self.segments(position);
+        if segments != self.segments {
+            let bounds = segments
+                .iter()
+                .chain(&self.segments)
+                .map(|&segment| segment_bounds(segment))
+                .collect::<Vec<_>>();
+
+            let crossed = self
+                .origins
+                .iter()
+                .filter(|(window_id, origin)| {
+                    let (_, framebuffer) = &self.graphics[window_id];
+                    let right = origin.x + framebuffer.width() as i32;
+                    let bottom = origin.y + framebuffer.height() as i32;
+
+                    bounds.iter().any(|bounds| {
+                        bounds.0 < right
+                            && bounds.1 < bottom
+                            && origin.x < bounds.2
+                            && origin.y < bounds.3
+                    })
+                })
+                .map(|&(window_id, _)| window_id)
+                .collect::<Vec<_>>();
+
+            self.pending_draws.extend(crossed);
+            self.segments = segments;
+        }
+
+        if let Some(lens_window) = self.lens_window {
+            self.request_draw(lens_window);
+        }
+
+        if let Some((window_id, _)) = position.and_then(|pos| self.locate(pos)) {
+            // Filled regions, pins and lines are described in a label next to the cursor
+            if self.should_display_zoom()
+                || self.region.is_some()
+                || !self.pins.is_empty()
+                || !self.segments.is_empty()
+            {
+                self.request_draw(window_id);
+            }
+        }
+    }
+
+    pub fn request_draw(&mut self, window_id: WindowId) {
+        self.pending_draws.insert(window_id);
+    }
+
+    pub fn request_all_draws(&mut self) {
+        self.pending_draws
+            .extend(self.windows.iter().map(Window::id));
+    }
+
+    /// Requests every scheduled redraw, so a burst of events only filters and draws each window
+    /// once
+    pub fn flush_draws(&mut self) {
+        if let Some((color, _)) = self
+            .next_highlight
+            .filter(|(_, since)| since.elapsed() >= HIGHLIGHT_DELAY)
+        {
+            self.next_highlight = None;
+            self.highlighted = Some(color);
+            self.invalidate_filter();
+        }
+
+        if self.filter_outdated {
+            self.apply_filter();
+        }
+
+        for window_id in self.pending_draws.drain() {
+            self.windows
+                .iter()
+                .find(|x| x.id() == window_id)
+                .unwrap()
+                .request_redraw();
+        }
+    }
+
+    /// Switches the lens to the next view mode
+    pub fn cycle_view(&mut self) {
+        self.lens_style.view = self.lens_style.view.next();
     }
 
     pub const fn should_display_zoom(&self) -> bool {
@@ -254,16 +1202,105 @@ fn get_ideal_video_mode(monitor: MonitorHandle) -> Option<VideoMode> {
     })
 }
 
-fn image_to_softbuffer(image: &DynamicImage) -> SoftBufferImage {
-    let buffer = image
-        .as_rgba8()
-        .map(|image| image.chunks(4))
-        // SAFETY: `screenshots` crate and flameshot should both be returning either RGBA8 or RGB8
-        .unwrap_or_else(|| image.as_rgb8().unwrap().chunks(3));
+/// Draws a pin at its position on the framebuffer, as the lens it was pinned with or as a cross,
+/// labelled with its color and number
+fn draw_pin(
+    framebuffer: &mut Framebuffer,
+    number: usize,
+    pin: &Pin,
+    local: PhysicalPosition<u32>,
+    lens_style: &LensStyle,
+    format: ColorFormat,
+) {
+    let (anchor, background) = match &pin.lens {
+        Some(lens) => {
+            let lens = framebuffer.draw_lens_pixels(
+                local.into(),
+                &lens.pixels,
+                lens.zoom,
+                lens.zoom_size,
+                lens_style,
+            );
+            (lens.rect, lens.border_color)
+        }
+        None => {
+            framebuffer.draw_cross(local.x.into(), local.y.into(), pin::PIN_COLOR);
+
+            let anchor = Rect {
+                x: local.x.saturating_sub(4),
+                y: local.y.saturating_sub(4),
+                width: 9,
+                height: 9,
+            };
+            (anchor, pin::PIN_COLOR)
+        }
+    };
+
+    let lines = [format.format(pin.pick.color), format!("pin {number}")];
+    let swatch = Some(framebuffer::from_rgb(pin.pick.color));
+
+    hud::draw_label(framebuffer, anchor, &lines, swatch, background);
+}
+
+/// A line between two global desktop positions
+type Segment = (PhysicalPosition<i32>, PhysicalPosition<i32>);
+
+/// The left, top, right and bottom edges of everything `draw_segment` draws for a segment,
+/// including the crosses at its ends
+fn segment_bounds((from, to): Segment) -> (i32, i32, i32, i32) {
+    (
+        from.x.min(to.x) - 4,
+        from.y.min(to.y) - 4,
+        from.x.max(to.x) + 5,
+        from.y.max(to.y) + 5,
+    )
+}
+
+/// Draws a line between two global positions with a cross on each end, `origin` being where the
+/// framebuffer's monitor starts
+fn draw_segment(
+    framebuffer: &mut Framebuffer,
+    origin: PhysicalPosition<i32>,
+    (from, to): Segment,
+    color: u32,
+) {
+    let local = |position: PhysicalPosition<i32>| {
+        (
+            (position.x - origin.x) as i64,
+            (position.y - origin.y) as i64,
+        )
+    };
+
+    let (from, to) = (local(from), local(to));
 
-    let buffer: SoftBufferImage = buffer
-        .map(|rgb| rgb[2] as u32 | ((rgb[1] as u32) << 8) | ((rgb[0] as u32) << 16))
-        .collect();
+    framebuffer.draw_line(from, to, color);
 
-    buffer
+    for (x, y) in [from, to] {
+        framebuffer.draw_cross(x, y, color);
+    }
+}
+
+/// Presents only what changed since the last frame, which is just the lens while moving
+fn present(surface: &mut Surface, framebuffer: &mut Framebuffer) {
+    let result = surface.buffer_mut().and_then(|mut buffer| {
+        let age = buffer.age();
+        let damage = framebuffer
+            .present_into(&mut buffer, age)
+            .into_iter()
+            .filter_map(|rect| {
+                Some(softbuffer::Rect {
+                    x: rect.x,
+                    y: rect.y,
+                    width: NonZeroU32::new(rect.width)?,
+                    height: NonZeroU32::new(rect.height)?,
+                })
+            })
+            .collect::<Vec<_>>();
+
+        buffer.present_with_damage(&damage)
+    });
+
+    if let Err(err) = result {
+        eprintln!("Could not present the frame: {err}");
+    }
 }
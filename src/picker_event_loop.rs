@@ -1,3 +1,5 @@
+use std::path::Path;
+
 use color_eyre::eyre::Result;
 use winit::{
     event::{
@@ -9,18 +11,57 @@ use winit::{
 };
 
 use crate::args::Args;
-use crate::picker_context::PickerContext;
+use crate::cvd::Deficiency;
+use crate::gradient::Gradient;
+use crate::measure::Measurement;
+use crate::picker_context::{Pick, PickerContext};
+use crate::text_color::TextColors;
+use crate::wand::Region;
+
+/// Where I saves the capture to without `--save-capture`
+const DEFAULT_CAPTURE_PATH: &str = "capture.png";
+/// Where shift and I saves the lens to without `--save-lens`
+const DEFAULT_LENS_PATH: &str = "lens.png";
+
+/// Why nothing was written by `PickerContext::save_capture`
+const NO_CAPTURE: &str = "the cursor is not on any monitor";
+/// Why nothing was written by `PickerContext::save_lens`
+const NO_LENS: &str = "the lens is not shown";
+
+/// What the picker was closed with
+#[derive(Debug, Clone, PartialEq)]
+pub enum Output {
+    Color(Pick),
+    /// Two picks to compare with `--contrast`
+    Contrast {
+        foreground: Pick,
+        background: Pick,
+    },
+    Measurement(Measurement),
+    Text(TextColors),
+    Gradient(Gradient),
+    Region(Region),
+}
 
-pub fn launch_picker_gui(args: &Args) -> Result<Option<(u8, u8, u8)>> {
+pub fn launch_picker_gui(args: &Args) -> Result<Option<Output>> {
     let mut event_loop: EventLoop<()> = EventLoop::new();
 
     let mut ctx = PickerContext::new(&event_loop, args)?;
 
     let mut position = None;
 
-    let mut color = None;
+    let mut output = None;
+
+    let mut modifiers = ModifiersState::empty();
+
+    // Whether CTRL was pressed on its own, which toggles zoom once it is released
+    let mut ctrl_tap = false;
 
-    let mut mouse_events = 0;
+    // Where the pointer was last warped to by keyboard movement
+    let mut warped_to = None;
+
+    // Where the cursor was when it left the picker's windows
+    let mut left_at = None;
 
     event_loop.run_return(|event, _, control_flow| {
         control_flow.set_wait();
@@ -34,18 +75,59 @@ pub fn launch_picker_gui(args: &Args) -> Result<Option<(u8, u8, u8)>> {
                     },
                 window_id,
             } => {
-                mouse_events += 1;
+                let new_position = ctx.to_global(window_id, new_position);
+
+                // The pointer following keyboard movement is not the mouse moving
+                if warped_to
+                    .take()
+                    .is_some_and(|warp| Some(warp) == new_position)
+                {
+                    return;
+                }
 
-                position = Some((new_position.cast::<u32>(), window_id));
+                position = new_position;
 
                 if ctx.should_display_zoom() {
                     ctx.set_cursor(true);
-                    // Prevents initial incorrect mouse position from sticking to other windows
-                    if mouse_events >= 5 {
-                        ctx.request_draw_all();
-                    } else {
-                        ctx.request_draw(window_id);
-                    }
+                }
+
+                ctx.update_highlight(position);
+                ctx.update_lens(position);
+            }
+            // Moving into another window is handled by its own `CursorMoved`, this only matters
+            // when the cursor leaves for somewhere the picker does not cover
+            Event::WindowEvent {
+                event: WindowEvent::CursorLeft { .. },
+                window_id,
+            } if position.and_then(|pos| ctx.locate(pos)).map(|(id, _)| id) == Some(window_id) => {
+                left_at = position.take();
+                ctx.update_lens(position);
+            }
+            // Entering does not say where, so coming back without moving is assumed to be at the
+            // same spot until the next `CursorMoved` says otherwise
+            Event::WindowEvent {
+                event: WindowEvent::CursorEntered { .. },
+                window_id,
+            } => {
+                let Some(entered_at) = left_at
+                    .take()
+                    .filter(|&pos| ctx.locate(pos).map(|(id, _)| id) == Some(window_id))
+                else {
+                    return;
+                };
+
+                if position.is_none() {
+                    position = Some(entered_at);
+
+                    ctx.update_highlight(position);
+                    ctx.update_lens(position);
+                }
+            }
+            Event::MainEventsCleared => ctx.flush_draws(),
+            // The last event before waiting, so nothing resets waking up for the highlight
+            Event::RedrawEventsCleared => {
+                if let Some(deadline) = ctx.highlight_deadline() {
+                    control_flow.set_wait_until(deadline);
                 }
             }
             Event::WindowEvent {
@@ -66,11 +148,75 @@ pub fn launch_picker_gui(args: &Args) -> Result<Option<(u8, u8, u8)>> {
                     },
                 ..
             } => {
-                let possible_color = position.and_then(|(pos, id)| ctx.get_pixel(&id, pos));
+                // Pressing again finishes a gradient that was clicked instead of dragged
+                if ctx.gradient_mode {
+                    match ctx.gradient(position) {
+                        Some(gradient) => {
+                            control_flow.set_exit();
+                            output = Some(Output::Gradient(gradient));
+                        }
+                        None => ctx.start_drag(position),
+                    }
+                    return;
+                }
+
+                // Clicking inside of the filled area outputs it, anywhere else fills from there
+                if ctx.wand_mode {
+                    match ctx.region_at(position) {
+                        Some(region) => {
+                            control_flow.set_exit();
+                            output = Some(Output::Region(region.clone()));
+                        }
+                        None => ctx.fill(position),
+                    }
+                    return;
+                }
+
+                if let Some(measurement) = ctx.measurement(position) {
+                    control_flow.set_exit();
+                    output = Some(Output::Measurement(measurement));
+                    return;
+                }
+
+                if ctx.text_mode {
+                    if let Some(text_colors) = position.and_then(|pos| ctx.text_colors(pos)) {
+                        control_flow.set_exit();
+                        output = Some(Output::Text(text_colors));
+                    }
+                    return;
+                }
+
+                let Some(pick) = position.and_then(|pos| ctx.get_pixel(pos)) else {
+                    return;
+                };
+
+                if args.contrast && ctx.foreground.is_none() {
+                    ctx.foreground = Some(pick);
+                    ctx.update_lens(position);
+                    return;
+                }
+
+                control_flow.set_exit();
 
-                if let Some(new_color) = possible_color {
+                output = Some(ctx.foreground.map_or(Output::Color(pick), |foreground| {
+                    Output::Contrast {
+                        foreground,
+                        background: pick,
+                    }
+                }));
+            }
+            Event::WindowEvent {
+                event:
+                    WindowEvent::MouseInput {
+                        state: ElementState::Released,
+                        button: MouseButton::Left,
+                        ..
+                    },
+                ..
+            } if ctx.gradient_mode => {
+                if let Some(gradient) = ctx.gradient(position) {
                     control_flow.set_exit();
-                    color = Some(new_color);
+                    output = Some(Output::Gradient(gradient));
                 }
             }
             Event::WindowEvent {
@@ -105,8 +251,249 @@ pub fn launch_picker_gui(args: &Args) -> Result<Option<(u8, u8, u8)>> {
                     ElementState::Released => false,
                 };
 
-                if let Some((_, window_id)) = position {
-                    ctx.request_draw(window_id);
+                ctx.update_lens(position);
+            }
+            Event::WindowEvent {
+                event:
+                    WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(VirtualKeyCode::V),
+                                ..
+                            },
+                        ..
+                    },
+                ..
+            } => {
+                ctrl_tap = false;
+
+                ctx.cycle_view();
+                ctx.update_lens(position);
+            }
+            Event::WindowEvent {
+                event:
+                    WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode:
+                                    Some(
+                                        key @ (VirtualKeyCode::Key1
+                                        | VirtualKeyCode::Key2
+                                        | VirtualKeyCode::Key3
+                                        | VirtualKeyCode::Key4),
+                                    ),
+                                ..
+                            },
+                        ..
+                    },
+                ..
+            } => {
+                ctrl_tap = false;
+
+                ctx.toggle_simulation(match key {
+                    VirtualKeyCode::Key1 => Deficiency::Protanopia,
+                    VirtualKeyCode::Key2 => Deficiency::Deuteranopia,
+                    VirtualKeyCode::Key3 => Deficiency::Tritanopia,
+                    _ => Deficiency::Achromatopsia,
+                });
+            }
+            Event::WindowEvent {
+                event:
+                    WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(VirtualKeyCode::M),
+                                ..
+                            },
+                        ..
+                    },
+                ..
+            } => {
+                ctrl_tap = false;
+
+                ctx.toggle_anchor(position);
+            }
+            Event::WindowEvent {
+                event:
+                    WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(VirtualKeyCode::N),
+                                ..
+                            },
+                        ..
+                    },
+                ..
+            } => {
+                ctrl_tap = false;
+
+                ctx.snap_edges = !ctx.snap_edges;
+                ctx.update_lens(position);
+            }
+            Event::WindowEvent {
+                event:
+                    WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(VirtualKeyCode::T),
+                                ..
+                            },
+                        ..
+                    },
+                ..
+            } => {
+                ctrl_tap = false;
+
+                ctx.text_mode = !ctx.text_mode;
+                ctx.update_lens(position);
+            }
+            Event::WindowEvent {
+                event:
+                    WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(VirtualKeyCode::G),
+                                ..
+                            },
+                        ..
+                    },
+                ..
+            } => {
+                ctrl_tap = false;
+
+                ctx.gradient_mode = !ctx.gradient_mode;
+                ctx.start_drag(None);
+            }
+            Event::WindowEvent {
+                event:
+                    WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(VirtualKeyCode::F),
+                                ..
+                            },
+                        ..
+                    },
+                ..
+            } => {
+                ctrl_tap = false;
+
+                ctx.wand_mode = !ctx.wand_mode;
+                ctx.fill(None);
+            }
+            Event::WindowEvent {
+                event:
+                    WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(VirtualKeyCode::C),
+                                ..
+                            },
+                        ..
+                    },
+                ..
+            } => {
+                ctrl_tap = false;
+
+                ctx.toggle_highlight(position);
+            }
+            Event::WindowEvent {
+                event:
+                    WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(VirtualKeyCode::P),
+                                ..
+                            },
+                        ..
+                    },
+                ..
+            } => {
+                ctrl_tap = false;
+
+                ctx.pin(position);
+            }
+            Event::WindowEvent {
+                event:
+                    WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(VirtualKeyCode::Back),
+                                ..
+                            },
+                        ..
+                    },
+                ..
+            } => {
+                ctrl_tap = false;
+
+                ctx.unpin();
+            }
+            Event::WindowEvent {
+                event:
+                    WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(VirtualKeyCode::E),
+                                ..
+                            },
+                        ..
+                    },
+                ..
+            } => {
+                ctrl_tap = false;
+
+                // Failing to write is only reported by the export, closing would lose every pin
+                if !ctx.export_spec_sheet(&args.spec_sheet, args.spec_table, args.spec_crop) {
+                    eprintln!("There are no pins to export, press P to pin a color first");
+                }
+            }
+            Event::WindowEvent {
+                event:
+                    WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(VirtualKeyCode::I),
+                                ..
+                            },
+                        ..
+                    },
+                ..
+            } => {
+                ctrl_tap = false;
+
+                let (path, saved, reason) = if modifiers.shift() {
+                    let path = args
+                        .save_lens
+                        .as_deref()
+                        .unwrap_or_else(|| Path::new(DEFAULT_LENS_PATH));
+
+                    (path, ctx.save_lens(path), NO_LENS)
+                } else {
+                    let path = args
+                        .save_capture
+                        .as_deref()
+                        .unwrap_or_else(|| Path::new(DEFAULT_CAPTURE_PATH));
+
+                    (path, ctx.save_capture(position, path), NO_CAPTURE)
+                };
+
+                match saved {
+                    Ok(true) => eprintln!("Saved {}", path.display()),
+                    Ok(false) => eprintln!("Nothing was saved to {}, {reason}", path.display()),
+                    Err(err) => eprintln!("{err}"),
                 }
             }
             Event::WindowEvent {
@@ -124,13 +511,19 @@ pub fn launch_picker_gui(args: &Args) -> Result<Option<(u8, u8, u8)>> {
                 }
             }
             Event::WindowEvent {
-                event: WindowEvent::ModifiersChanged(ModifiersState::CTRL),
+                event: WindowEvent::ModifiersChanged(new_modifiers),
                 ..
             } => {
-                ctx.toggle_zoom = !ctx.toggle_zoom;
+                let released_ctrl_tap =
+                    ctrl_tap && modifiers == ModifiersState::CTRL && new_modifiers.is_empty();
+
+                ctrl_tap = modifiers.is_empty() && new_modifiers == ModifiersState::CTRL;
+                modifiers = new_modifiers;
 
-                if let Some((_, window_id)) = position {
-                    ctx.request_draw(window_id);
+                if released_ctrl_tap {
+                    ctx.toggle_zoom = !ctx.toggle_zoom;
+
+                    ctx.update_lens(position);
                 }
             }
             Event::WindowEvent {
@@ -141,30 +534,19 @@ pub fn launch_picker_gui(args: &Args) -> Result<Option<(u8, u8, u8)>> {
                         ..
                     },
                 ..
-            } => {
-                if ctx.should_display_zoom() {
-                    if ctx.hold_right_click {
-                        ctx.change_zoom_size(vertical_amount);
-                    } else {
-                        ctx.change_zoom(vertical_amount);
-                    }
-
-                    if let Some((_, window_id)) = position {
-                        ctx.request_draw(window_id);
-                    }
+            } if ctx.should_display_zoom() => {
+                if ctx.hold_right_click {
+                    ctx.change_zoom_size(vertical_amount);
+                } else {
+                    ctx.change_zoom(vertical_amount);
                 }
+
+                ctx.update_lens(position);
             }
             Event::RedrawRequested(window_id) => {
-                if let Some((pos, cursor_window)) = position {
-                    if ctx.should_display_zoom() && cursor_window == window_id {
-                        ctx.redraw_window(window_id, pos)
-                            .unwrap_or_else(|| ctx.draw_empty_window(window_id));
-                    } else {
-                        ctx.draw_empty_window(window_id);
-                    }
-                } else {
-                    ctx.draw_empty_window(window_id);
-                }
+                ctx.draw_window(window_id, position);
+
+                ctx.timings.finish("first frame since startup");
             }
             Event::WindowEvent {
                 event:
@@ -179,25 +561,71 @@ pub fn launch_picker_gui(args: &Args) -> Result<Option<(u8, u8, u8)>> {
                     },
                 ..
             } => {
-                let (pos, window_id) = match position {
-                    Some((ref mut pos, window_id)) => (pos, window_id),
-                    _ => return,
+                let Some(pos) = position else {
+                    return;
                 };
 
-                match key {
-                    VirtualKeyCode::A | VirtualKeyCode::H | VirtualKeyCode::Left => pos.x -= 1,
-                    VirtualKeyCode::S | VirtualKeyCode::J | VirtualKeyCode::Down => pos.y += 1,
-                    VirtualKeyCode::W | VirtualKeyCode::K | VirtualKeyCode::Up => pos.y -= 1,
-                    VirtualKeyCode::D | VirtualKeyCode::L | VirtualKeyCode::Right => pos.x += 1,
+                let (dx, dy) = match key {
+                    VirtualKeyCode::A | VirtualKeyCode::H | VirtualKeyCode::Left => (-1, 0),
+                    VirtualKeyCode::S | VirtualKeyCode::J | VirtualKeyCode::Down => (0, 1),
+                    VirtualKeyCode::W | VirtualKeyCode::K | VirtualKeyCode::Up => (0, -1),
+                    VirtualKeyCode::D | VirtualKeyCode::L | VirtualKeyCode::Right => (1, 0),
                     _ => return,
                 };
 
+                let step = if modifiers.ctrl() {
+                    100
+                } else if modifiers.shift() {
+                    10
+                } else {
+                    1
+                };
+
+                // CTRL was used for a bigger step, so releasing it should not toggle zoom
+                ctrl_tap = false;
+
+                let new_pos = if modifiers.alt() {
+                    ctx.next_edge(pos, dx, dy)
+                } else {
+                    ctx.step(pos, dx * step, dy * step)
+                };
+                position = Some(new_pos);
+
+                if ctx.warp_cursor(new_pos) {
+                    warped_to = Some(new_pos);
+                }
+
                 ctx.set_cursor(false);
-                ctx.request_draw(window_id);
+
+                ctx.update_highlight(position);
+                ctx.update_lens(position);
             }
             _ => (),
         }
     });
 
-    Ok(color)
+    ctx.finish_exports();
+
+    if output.is_some() {
+        // Asking for a file that is then not written should not go unnoticed
+        if let Some(path) = &args.save_capture {
+            if !ctx.save_capture(position, path)? {
+                eprintln!(
+                    "Warning: nothing was saved to {}, {NO_CAPTURE}",
+                    path.display()
+                );
+            }
+        }
+
+        if let Some(path) = &args.save_lens {
+            if !ctx.save_lens(path)? {
+                eprintln!(
+                    "Warning: nothing was saved to {}, {NO_LENS}",
+                    path.display()
+                );
+            }
+        }
+    }
+
+    Ok(output)
 }
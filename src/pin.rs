@@ -0,0 +1,47 @@
+use winit::dpi::PhysicalPosition;
+
+use crate::delta_e;
+use crate::picker_context::Pick;
+
+/// The overlay color of a pin without a lens and of the comparison with it
+pub const PIN_COLOR: u32 = 0xFF8000;
+
+/// A pick left on the screen to compare the cursor against
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pin {
+    pub position: PhysicalPosition<i32>,
+    pub pick: Pick,
+    /// The lens it was pinned with, if the lens was shown
+    pub lens: Option<PinnedLens>,
+}
+
+/// A lens as it was shown when pinning, so it keeps showing that while the screen changes under
+/// it, like when a filter is turned on
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PinnedLens {
+    pub zoom: u32,
+    pub zoom_size: u32,
+    /// What each cell showed row by row, from `Framebuffer::lens_pixels`
+    pub pixels: Vec<Option<u32>>,
+}
+
+impl Pin {
+    /// The lines comparing the pinned color with `color`, the differences going from the pin to it
+    pub fn comparison(&self, number: usize, color: (u8, u8, u8)) -> [String; 2] {
+        let pinned = self.pick.color;
+        let difference = |from: u8, to: u8| i16::from(to) - i16::from(from);
+
+        [
+            format!(
+                "ΔE: {:.2} to pin {number}",
+                delta_e::ciede2000(pinned, color)
+            ),
+            format!(
+                "Δ: {:+} {:+} {:+}",
+                difference(pinned.0, color.0),
+                difference(pinned.1, color.1),
+                difference(pinned.2, color.2)
+            ),
+        ]
+    }
+}
@@ -0,0 +1,37 @@
+use crate::args::SampleMode;
+use crate::framebuffer::{from_rgb, to_rgb};
+use crate::srgb;
+
+/// Combines the pixels of a sampled area into the one color a pick reports, `None` if there are
+/// no pixels
+pub fn combine(mode: SampleMode, pixels: &[u32]) -> Option<u32> {
+    if pixels.is_empty() {
+        return None;
+    }
+
+    let channel = |index: usize| {
+        pixels
+            .iter()
+            .map(move |&pixel| <[u8; 3]>::from(to_rgb(pixel))[index])
+    };
+    let count = pixels.len() as f32;
+
+    let combined = [0, 1, 2].map(|index| match mode {
+        SampleMode::Mean => (channel(index).map(f32::from).sum::<f32>() / count).round() as u8,
+        SampleMode::Median => {
+            let mut values = channel(index).collect::<Vec<_>>();
+            values.sort_unstable();
+            values[values.len() / 2]
+        }
+        SampleMode::GammaMean => {
+            let light = channel(index)
+                .map(|value| srgb::to_linear(f32::from(value) / 255.0))
+                .sum::<f32>()
+                / count;
+
+            (srgb::from_linear(light) * 255.0).round() as u8
+        }
+    });
+
+    Some(from_rgb(combined.into()))
+}
@@ -1,24 +1,67 @@
 use std::process::Command;
 
 use color_eyre::Result;
-use image::{DynamicImage, ImageFormat};
+use image::ImageFormat;
 use winit::monitor::MonitorHandle;
 
+use super::capture_parallel;
+use crate::framebuffer::{Framebuffer, Rect};
+use crate::timings::Timings;
+
 /// This will return in the same order as the given `monitors`
-pub fn screenshots_ordered(monitors: &[MonitorHandle]) -> Result<Vec<DynamicImage>> {
-    let output = Command::new("flameshot")
-        .args(["full", "--raw"])
-        .output()?
-        .stdout;
+pub fn screenshots_ordered(
+    monitors: &[MonitorHandle],
+    timings: &Timings,
+) -> Result<Vec<Framebuffer>> {
+    let output = timings.time("flameshot capture", || {
+        Command::new("flameshot").args(["full", "--raw"]).output()
+    })?;
 
-    let full = image::load_from_memory_with_format(&output, ImageFormat::Png)?;
+    // Flameshot can only output encoded images, PNG is the cheapest of them to decode
+    let full = timings.time("flameshot decode", || {
+        image::load_from_memory_with_format(&output.stdout, ImageFormat::Png)
+    })?;
 
-    Ok(monitors
+    // The capture starts at the top left of the desktop, which can be left of or above the
+    // primary monitor, and is in logical pixels when the desktop is scaled
+    let left = monitors.iter().map(|x| x.position().x).min().unwrap_or(0);
+    let top = monitors.iter().map(|x| x.position().y).min().unwrap_or(0);
+    let right = monitors
+        .iter()
+        .map(|x| x.position().x + x.size().width as i32)
+        .max()
+        .unwrap_or(0);
+    let bottom = monitors
         .iter()
-        .map(|monitor| {
-            let pos = monitor.position().cast();
-            let size = monitor.size();
-            full.crop_imm(pos.x, pos.y, size.width, size.height)
-        })
-        .collect())
+        .map(|x| x.position().y + x.size().height as i32)
+        .max()
+        .unwrap_or(0);
+
+    let scale_x = full.width() as f64 / (right - left).max(1) as f64;
+    let scale_y = full.height() as f64 / (bottom - top).max(1) as f64;
+
+    let scale_to_full =
+        |value: i32, scale: f64, limit: u32| ((value as f64 * scale).round() as u32).min(limit);
+
+    let jobs = monitors.iter().map(|monitor| {
+        let pos = monitor.position();
+        let size = monitor.size();
+
+        let x = scale_to_full(pos.x - left, scale_x, full.width());
+        let y = scale_to_full(pos.y - top, scale_y, full.height());
+        let area = Rect {
+            x,
+            y,
+            width: scale_to_full(size.width as i32, scale_x, full.width() - x),
+            height: scale_to_full(size.height as i32, scale_y, full.height() - y),
+        };
+
+        (area, size)
+    });
+
+    capture_parallel(jobs, |index, (area, size)| {
+        Ok(timings.time(format_args!("monitor {index} convert"), || {
+            Framebuffer::from_image_area(&full, area).resized(size.width, size.height)
+        }))
+    })
 }
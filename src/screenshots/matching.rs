@@ -0,0 +1,249 @@
+use std::fmt::{self, Display};
+
+use color_eyre::{eyre::eyre, Result};
+use winit::monitor::MonitorHandle;
+
+/// Geometry of a screen as reported by a capture backend or by winit.
+///
+/// Backends are free to report logical coordinates, the scale factor is used to relate them to
+/// winit's physical ones.
+#[derive(Debug, Clone)]
+pub struct ScreenInfo {
+    pub name: Option<String>,
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    pub scale_factor: f64,
+}
+
+impl ScreenInfo {
+    pub fn from_monitor(monitor: &MonitorHandle) -> Self {
+        let position = monitor.position();
+        let size = monitor.size();
+
+        Self {
+            name: monitor.name(),
+            x: position.x,
+            y: position.y,
+            width: size.width,
+            height: size.height,
+            scale_factor: monitor.scale_factor(),
+        }
+    }
+
+    /// Distance in physical pixels between the two screens' rectangles, trying `other` as both
+    /// physical and logical coordinates.
+    fn distance(&self, other: &Self) -> f64 {
+        [1.0, other.scale_factor, self.scale_factor]
+            .into_iter()
+            .map(|scale| {
+                let gap = |own: f64, other: f64| other.mul_add(-scale, own).abs();
+
+                gap(self.x as f64, other.x as f64)
+                    + gap(self.y as f64, other.y as f64)
+                    + gap(self.width as f64, other.width as f64)
+                    + gap(self.height as f64, other.height as f64)
+            })
+            .fold(f64::INFINITY, f64::min)
+    }
+}
+
+impl Display for ScreenInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} at ({}, {}) size {}x{} scale {}",
+            self.name.as_deref().unwrap_or("<unnamed>"),
+            self.x,
+            self.y,
+            self.width,
+            self.height,
+            self.scale_factor
+        )
+    }
+}
+
+/// Rounding of fractional scale factors can leave a couple of pixels of error on every edge.
+const GEOMETRY_TOLERANCE: f64 = 8.0;
+
+/// Pairs every monitor with one of the `screens` of a backend, returning the index of the screen
+/// for each monitor in order.
+///
+/// Screens are matched by name first, then by the closest geometry under either coordinate space
+/// and as a last resort by their arrangement on the desktop if only that is left to go by, which is
+/// warned about. Backends that cannot name their screens, like `screenshots`, skip straight to the
+/// geometry.
+pub fn match_screens(monitors: &[MonitorHandle], screens: &[ScreenInfo]) -> Result<Vec<usize>> {
+    let monitors = monitors
+        .iter()
+        .map(ScreenInfo::from_monitor)
+        .collect::<Vec<_>>();
+
+    match_infos(&monitors, screens)
+}
+
+fn match_infos(monitors: &[ScreenInfo], screens: &[ScreenInfo]) -> Result<Vec<usize>> {
+    let mut matches: Vec<Option<usize>> = vec![None; monitors.len()];
+    let mut taken = vec![false; screens.len()];
+
+    for (monitor, matched) in monitors.iter().zip(&mut matches) {
+        let by_name = (0..screens.len()).find(|&index| {
+            !taken[index] && monitor.name.is_some() && screens[index].name == monitor.name
+        });
+
+        if let Some(index) = by_name {
+            taken[index] = true;
+            *matched = Some(index);
+        }
+    }
+
+    let mut candidates = Vec::new();
+    for (monitor_index, monitor) in monitors.iter().enumerate() {
+        for (screen_index, screen) in screens.iter().enumerate() {
+            let distance = monitor.distance(screen);
+            if distance <= GEOMETRY_TOLERANCE * monitor.scale_factor.max(1.0) {
+                candidates.push((distance, monitor_index, screen_index));
+            }
+        }
+    }
+    candidates.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    for (_, monitor_index, screen_index) in candidates {
+        if matches[monitor_index].is_none() && !taken[screen_index] {
+            taken[screen_index] = true;
+            matches[monitor_index] = Some(screen_index);
+        }
+    }
+
+    let mut unmatched_monitors = (0..monitors.len())
+        .filter(|&index| matches[index].is_none())
+        .collect::<Vec<_>>();
+    let mut unmatched_screens = (0..screens.len())
+        .filter(|&index| !taken[index])
+        .collect::<Vec<_>>();
+
+    // Scaling the whole desktop keeps the screens' order, even when nothing else lines up. It is
+    // only a guess though, so every pair made this way is reported
+    if !unmatched_monitors.is_empty() && unmatched_monitors.len() == unmatched_screens.len() {
+        unmatched_monitors.sort_by_key(|&index| (monitors[index].x, monitors[index].y));
+        unmatched_screens.sort_by_key(|&index| (screens[index].x, screens[index].y));
+
+        eprintln!(
+            "Warning: some captured screens do not match any monitor, pairing them by order:"
+        );
+
+        for (&monitor_index, &screen_index) in unmatched_monitors.iter().zip(&unmatched_screens) {
+            eprintln!(
+                "  monitor {} with screen {}",
+                monitors[monitor_index], screens[screen_index]
+            );
+            matches[monitor_index] = Some(screen_index);
+        }
+    }
+
+    if let Some(matches) = matches.iter().copied().collect::<Option<Vec<_>>>() {
+        return Ok(matches);
+    }
+
+    Err(eyre!(
+        "Captured screens do not match winit monitors!\n  \
+        Unmatched monitors:{}\n  \
+        Unmatched screens:{}\n  \
+        There are {} monitors but {} screens, so they could not be paired up by their order either",
+        describe(unmatched_monitors.iter().map(|&index| &monitors[index])),
+        describe(unmatched_screens.iter().map(|&index| &screens[index])),
+        monitors.len(),
+        screens.len()
+    ))
+}
+
+fn describe<'a>(screens: impl Iterator<Item = &'a ScreenInfo>) -> String {
+    screens.map(|screen| format!("\n    {screen}")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn screen(name: Option<&str>, (x, y): (i32, i32), (width, height): (u32, u32)) -> ScreenInfo {
+        ScreenInfo {
+            name: name.map(str::to_owned),
+            x,
+            y,
+            width,
+            height,
+            scale_factor: 1.0,
+        }
+    }
+
+    #[test]
+    fn matches_by_name_over_geometry() {
+        let monitors = [
+            screen(Some("DP-1"), (0, 0), (1920, 1080)),
+            screen(Some("HDMI-1"), (1920, 0), (1920, 1080)),
+        ];
+        // The geometry is swapped, only the names say which is which
+        let screens = [
+            screen(Some("HDMI-1"), (0, 0), (1920, 1080)),
+            screen(Some("DP-1"), (1920, 0), (1920, 1080)),
+        ];
+
+        assert_eq!(match_infos(&monitors, &screens).unwrap(), [1, 0]);
+    }
+
+    #[test]
+    fn matches_logical_geometry_under_fractional_scaling() {
+        let monitors = [
+            ScreenInfo {
+                scale_factor: 1.5,
+                ..screen(None, (0, 0), (2880, 1620))
+            },
+            screen(None, (2880, 0), (1920, 1080)),
+        ];
+        // Reported in logical pixels, with the rounding a fractional scale leaves
+        let screens = [
+            screen(None, (2880, 0), (1920, 1080)),
+            ScreenInfo {
+                scale_factor: 1.5,
+                ..screen(None, (0, 0), (1921, 1079))
+            },
+        ];
+
+        assert_eq!(match_infos(&monitors, &screens).unwrap(), [1, 0]);
+    }
+
+    #[test]
+    fn falls_back_to_the_order_on_the_desktop() {
+        let monitors = [
+            screen(None, (1000, 0), (800, 600)),
+            screen(None, (0, 0), (1000, 800)),
+        ];
+        let screens = [
+            screen(None, (0, 0), (500, 400)),
+            screen(None, (500, 0), (400, 300)),
+        ];
+
+        assert_eq!(match_infos(&monitors, &screens).unwrap(), [1, 0]);
+    }
+
+    #[test]
+    fn explains_leftover_monitors() {
+        let monitors = [
+            screen(Some("DP-1"), (0, 0), (1920, 1080)),
+            screen(Some("DP-2"), (1920, 0), (1920, 1080)),
+        ];
+        let screens = [screen(None, (0, 0), (1920, 1080))];
+
+        let error = match_infos(&monitors, &screens).unwrap_err().to_string();
+
+        assert!(
+            error.contains("Unmatched monitors:\n    DP-2 at (1920, 0)"),
+            "{error}"
+        );
+        assert!(
+            error.contains("There are 2 monitors but 1 screens"),
+            "{error}"
+        );
+    }
+}
@@ -1,3 +1,16 @@
+use std::thread;
+
+use color_eyre::{eyre::eyre, Result};
+
+use crate::framebuffer::Framebuffer;
+
+// Flameshot captures the whole desktop at once, so it has no screens to pair up
+#[cfg_attr(
+    not(any(feature = "screenshots_crate", feature = "x11")),
+    allow(dead_code)
+)]
+mod matching;
+
 #[cfg(feature = "screenshots_crate")]
 mod screenshots_crate;
 #[cfg(feature = "screenshots_crate")]
@@ -11,4 +24,36 @@ pub use flameshot::screenshots_ordered;
 #[cfg(feature = "x11")]
 mod x11_impl;
 #[cfg(feature = "x11")]
-pub use x11_impl::screenshots_ordered;
+pub use x11_impl::{screenshots_ordered, XDisplay};
+
+/// Runs `capture` on its own thread for every job, with the job's index, and returns the
+/// framebuffers in the order of the jobs
+#[cfg_attr(
+    not(any(feature = "screenshots_crate", feature = "flameshot", feature = "x11")),
+    allow(dead_code)
+)]
+fn capture_parallel<T: Send>(
+    jobs: impl IntoIterator<Item = T>,
+    capture: impl Fn(usize, T) -> Result<Framebuffer> + Sync,
+) -> Result<Vec<Framebuffer>> {
+    let capture = &capture;
+
+    thread::scope(|scope| {
+        // Every thread has to be spawned before any is joined for them to run in parallel
+        #[allow(clippy::needless_collect)]
+        let threads = jobs
+            .into_iter()
+            .enumerate()
+            .map(|(index, job)| scope.spawn(move || capture(index, job)))
+            .collect::<Vec<_>>();
+
+        threads
+            .into_iter()
+            .map(|thread| {
+                thread
+                    .join()
+                    .map_err(|_| eyre!("Capturing a monitor panicked!"))?
+            })
+            .collect()
+    })
+}
@@ -1,30 +1,53 @@
-use std::collections::HashMap;
-
 use color_eyre::{eyre::eyre, Result};
-use image::{DynamicImage, ImageFormat};
+use image::ImageFormat;
 use screenshots::Screen;
 use winit::monitor::MonitorHandle;
 
+use super::capture_parallel;
+use super::matching::{match_screens, ScreenInfo};
+use crate::framebuffer::Framebuffer;
+use crate::timings::Timings;
+
 /// This will return in the same order as the given `monitors`
-pub fn screenshots_ordered(monitors: &[MonitorHandle]) -> Result<Vec<DynamicImage>> {
-    let mut screens = Screen::all()
-        .map_err(|err| eyre!(err))?
-        .into_iter()
-        .map(|screen| ((screen.display_info.x, screen.display_info.y), screen))
-        .collect::<HashMap<_, _>>();
-
-    monitors
+pub fn screenshots_ordered(
+    monitors: &[MonitorHandle],
+    timings: &Timings,
+) -> Result<Vec<Framebuffer>> {
+    let screens = Screen::all().map_err(|err| eyre!(err))?;
+
+    // `display-info` only identifies screens by an output id on X11 and a display id elsewhere,
+    // neither of which winit exposes as a name, so there is only the geometry to go by
+    let infos = screens
         .iter()
-        .map(|monitor| monitor.position())
-        .map(|pos| {
-            let (_, screen) = screens
-                .remove_entry(&(pos.x, pos.y))
-                .ok_or(eyre!("`screenshots` screens do not match winit monitors!"))?;
+        .map(|screen| ScreenInfo {
+            name: None,
+            x: screen.display_info.x,
+            y: screen.display_info.y,
+            width: screen.display_info.width,
+            height: screen.display_info.height,
+            scale_factor: screen.display_info.scale_factor as f64,
+        })
+        .collect::<Vec<_>>();
 
-            let capture = screen.capture().map_err(|err| eyre!(err))?;
+    let matches = match_screens(monitors, &infos)?;
 
+    // `screenshots` only hands out PNGs, so every monitor is decoded on its own thread
+    let jobs = monitors
+        .iter()
+        .zip(matches)
+        .map(|(monitor, screen_index)| (screens[screen_index], monitor.size()));
+
+    capture_parallel(jobs, |index, (screen, size)| {
+        let capture = timings
+            .time(format_args!("monitor {index} capture"), || screen.capture())
+            .map_err(|err| eyre!(err))?;
+
+        let image = timings.time(format_args!("monitor {index} decode"), || {
             image::load_from_memory_with_format(capture.buffer(), ImageFormat::Png)
-                .map_err(Into::into)
-        })
-        .collect::<Result<Vec<_>>>()
+        })?;
+
+        Ok(timings.time(format_args!("monitor {index} convert"), || {
+            Framebuffer::from_image(&image).resized(size.width, size.height)
+        }))
+    })
 }
@@ -1,30 +1,77 @@
 use color_eyre::{eyre::eyre, Result};
-use image::{DynamicImage, RgbImage};
-use std::{ptr, slice};
+use std::{
+    ffi::CStr,
+    os::raw::{c_int, c_ulong},
+    ptr, slice,
+};
 use winit::monitor::MonitorHandle;
-use x11::xlib;
+use x11::{xlib, xrandr, xtest};
+
+use super::capture_parallel;
+use super::matching::{match_screens, ScreenInfo};
+use crate::framebuffer::{self, Framebuffer};
+use crate::timings::Timings;
 
 /// This will return in the same order as the given `monitors`
-pub fn screenshots_ordered(monitors: &[MonitorHandle]) -> Result<Vec<DynamicImage>> {
-    let screen = Screen::open().ok_or(eyre!("Could not create screen!"))?;
+pub fn screenshots_ordered(
+    monitors: &[MonitorHandle],
+    timings: &Timings,
+) -> Result<Vec<Framebuffer>> {
+    let display = XDisplay::open().ok_or_else(|| eyre!("Could not open the X11 display!"))?;
 
-    monitors
-        .iter()
-        .map(|monitor| (monitor.position(), monitor.size()))
-        .map(|(pos, size)| {
-            let image = screen
-                .capture_area(size.width, size.height, pos.x, pos.y)
-                .ok_or(eyre!("Could not capture area!"))?;
+    let crtcs = display.crtcs();
 
-            Ok(DynamicImage::ImageRgb8(image))
+    // Winit creates one monitor per active CRTC and names it after the CRTC's first output
+    let infos = crtcs
+        .iter()
+        .map(|crtc| ScreenInfo {
+            name: crtc.outputs.first().cloned(),
+            x: crtc.x,
+            y: crtc.y,
+            width: crtc.width,
+            height: crtc.height,
+            scale_factor: 1.0,
         })
-        .collect::<Result<Vec<_>>>()
+        .collect::<Vec<_>>();
+
+    let matches = match_screens(monitors, &infos)?;
+
+    let jobs = monitors
+        .iter()
+        .zip(matches)
+        .map(|(monitor, crtc_index)| (&crtcs[crtc_index], monitor.size()));
+
+    // Xlib connections can't be shared between threads, so every capture opens its own
+    capture_parallel(jobs, |index, (crtc, size)| {
+        let display = XDisplay::open().ok_or_else(|| eyre!("Could not open the X11 display!"))?;
+
+        let framebuffer = timings
+            .time(format_args!("monitor {index} capture"), || {
+                display.capture_area(crtc.root, crtc.width, crtc.height, crtc.x, crtc.y)
+            })
+            .ok_or_else(|| eyre!("Could not capture area!"))?;
+
+        Ok(framebuffer.resized(size.width, size.height))
+    })
 }
 
-/// A handle to an X11 screen.
-pub struct Screen {
+/// The area of an X screen scanned out by one CRTC.
+///
+/// The size is already rotated, and the root window holds the unrotated desktop contents, so
+/// capturing this rectangle of `root` gives exactly what is shown on the outputs.
+#[derive(Debug)]
+struct Crtc {
+    root: xlib::Window,
+    outputs: Vec<String>,
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+}
+
+/// A handle to an X11 display connection, opened from `DISPLAY`.
+pub struct XDisplay {
     display: *mut xlib::Display,
-    window: xlib::Window,
 }
 
 #[derive(Debug)]
@@ -35,65 +82,238 @@ struct Bgr {
     _pad: u8,
 }
 
-impl Screen {
-    /// Tries to open the X11 display, then returns a handle to the default screen.
+impl XDisplay {
+    /// Tries to open the X11 display named by `DISPLAY`, including its screen number.
     ///
     /// Returns `None` if the display could not be opened.
     pub fn open() -> Option<Self> {
-        unsafe {
-            let display = xlib::XOpenDisplay(ptr::null());
+        let display = unsafe { xlib::XOpenDisplay(ptr::null()) };
 
-            if display.is_null() {
-                return None;
-            }
+        if display.is_null() {
+            return None;
+        }
+
+        Some(Self { display })
+    }
+
+    /// Lists the active CRTCs of every X screen, with the default screen first so it wins any
+    /// geometry ties.
+    ///
+    /// Screens without RandR are listed as a single CRTC covering the whole root window.
+    fn crtcs(&self) -> Vec<Crtc> {
+        let default_screen = unsafe { xlib::XDefaultScreen(self.display) };
+        let screen_count = unsafe { xlib::XScreenCount(self.display) };
+
+        let mut screens = (0..screen_count).collect::<Vec<_>>();
+        screens.sort_by_key(|&screen| screen != default_screen);
+
+        screens
+            .into_iter()
+            .flat_map(|screen| {
+                let root = unsafe { xlib::XRootWindow(self.display, screen) };
+
+                self.randr_crtcs(root)
+                    .filter(|crtcs| !crtcs.is_empty())
+                    .unwrap_or_else(|| vec![self.whole_screen(screen, root)])
+            })
+            .collect()
+    }
+
+    fn has_randr(&self) -> bool {
+        let (mut event_base, mut error_base) = (0, 0);
+        unsafe { xrandr::XRRQueryExtension(self.display, &mut event_base, &mut error_base) != 0 }
+    }
+
+    fn randr_crtcs(&self, root: xlib::Window) -> Option<Vec<Crtc>> {
+        if !self.has_randr() {
+            return None;
+        }
+
+        let resources = unsafe { xrandr::XRRGetScreenResourcesCurrent(self.display, root) };
+
+        if resources.is_null() {
+            return None;
+        }
+
+        let crtc_ids = unsafe { raw_slice((*resources).crtcs, (*resources).ncrtc) };
+
+        let crtcs = crtc_ids
+            .iter()
+            .filter_map(|&crtc_id| unsafe {
+                let info = xrandr::XRRGetCrtcInfo(self.display, resources, crtc_id);
+
+                if info.is_null() {
+                    return None;
+                }
 
-            let screen = xlib::XDefaultScreenOfDisplay(display);
-            let root = xlib::XRootWindowOfScreen(screen);
+                let crtc = &*info;
+                let active = crtc.width > 0 && crtc.height > 0 && crtc.noutput > 0;
 
-            Some(Self {
-                display,
-                window: root,
+                let result = active.then(|| Crtc {
+                    root,
+                    outputs: raw_slice(crtc.outputs, crtc.noutput)
+                        .iter()
+                        .filter_map(|&output| self.output_name(resources, output))
+                        .collect(),
+                    x: crtc.x,
+                    y: crtc.y,
+                    width: crtc.width,
+                    height: crtc.height,
+                });
+
+                xrandr::XRRFreeCrtcInfo(info);
+                result
             })
+            .collect();
+
+        unsafe { xrandr::XRRFreeScreenResources(resources) };
+
+        Some(crtcs)
+    }
+
+    unsafe fn output_name(
+        &self,
+        resources: *mut xrandr::XRRScreenResources,
+        output: xrandr::RROutput,
+    ) -> Option<String> {
+        let info = xrandr::XRRGetOutputInfo(self.display, resources, output);
+
+        // Virtual outputs can fail to be queried, those simply have no name to match against
+        if info.is_null() {
+            return None;
         }
+
+        let name = CStr::from_ptr((*info).name).to_string_lossy().into_owned();
+        xrandr::XRRFreeOutputInfo(info);
+
+        Some(name)
     }
 
-    /// Tries to capture a screenshot of the provided area.
+    fn whole_screen(&self, screen: c_int, root: xlib::Window) -> Crtc {
+        let (width, height) = unsafe {
+            let screen = xlib::XScreenOfDisplay(self.display, screen);
+            (xlib::XWidthOfScreen(screen), xlib::XHeightOfScreen(screen))
+        };
+
+        Crtc {
+            root,
+            outputs: Vec::new(),
+            x: 0,
+            y: 0,
+            width: width as u32,
+            height: height as u32,
+        }
+    }
+
+    /// Moves the pointer to a position on the default screen through XTest, as if the mouse had
+    /// moved there.
     ///
-    /// Returns an `RgbImage` on success, `None` on failure.
+    /// Returns `false` if the XTest extension is not available.
+    pub fn warp_pointer(&self, x: i32, y: i32) -> bool {
+        let (mut event_base, mut error_base, mut major, mut minor) = (0, 0, 0, 0);
+
+        unsafe {
+            let has_xtest = xtest::XTestQueryExtension(
+                self.display,
+                &mut event_base,
+                &mut error_base,
+                &mut major,
+                &mut minor,
+            ) != 0;
+
+            if !has_xtest {
+                return false;
+            }
+
+            xtest::XTestFakeMotionEvent(self.display, -1, x, y, xlib::CurrentTime);
+            xlib::XFlush(self.display);
+        }
+
+        true
+    }
+
+    /// Tries to capture a screenshot of the provided area of `window`.
     ///
-    /// See the documentation of the `image` crate on how to use `RgbImage`.
-    pub fn capture_area(&self, w: u32, h: u32, x: i32, y: i32) -> Option<RgbImage> {
-        let img =
-            unsafe { xlib::XGetImage(self.display, self.window, x, y, w, h, !1, xlib::ZPixmap) };
+    /// Returns the pixels packed for presenting on success, `None` on failure.
+    pub fn capture_area(
+        &self,
+        window: xlib::Window,
+        w: u32,
+        h: u32,
+        x: i32,
+        y: i32,
+    ) -> Option<Framebuffer> {
+        let img = unsafe { xlib::XGetImage(self.display, window, x, y, w, h, !1, xlib::ZPixmap) };
 
         if img.is_null() {
             return None;
         }
 
         let image = unsafe { &mut *img };
-        let sl: &[Bgr] = unsafe {
-            slice::from_raw_parts(
-                (image).data as *const _,
-                (image).width as usize * (image).height as usize,
-            )
-        };
+        let mut pixels = Vec::with_capacity(w as usize * h as usize);
 
-        let mut bgr_iter = sl.iter();
-        let mut image_buffer = RgbImage::new(w, h);
+        // Reading the pixels as BGR bytes only works when they are stored least significant first
+        let is_bgr = image.bits_per_pixel == 32
+            && image.byte_order == xlib::LSBFirst
+            && image.red_mask == 0xFF0000
+            && image.blue_mask == 0xFF;
 
-        for pix in image_buffer.pixels_mut() {
-            let bgr = bgr_iter.next().unwrap();
-            pix.0 = [bgr.r, bgr.g, bgr.b];
+        if is_bgr {
+            for y in 0..h as usize {
+                // Rows can be padded past `width`, so each one is looked up by `bytes_per_line`
+                let sl: &[Bgr] = unsafe {
+                    slice::from_raw_parts(
+                        image.data.add(y * image.bytes_per_line as usize) as *const _,
+                        w as usize,
+                    )
+                };
+
+                pixels.extend(
+                    sl.iter()
+                        .map(|bgr| framebuffer::from_rgb((bgr.r, bgr.g, bgr.b))),
+                );
+            }
+        } else {
+            let masks = [image.red_mask, image.green_mask, image.blue_mask];
+
+            for y in 0..h as c_int {
+                for x in 0..w as c_int {
+                    let value = unsafe { xlib::XGetPixel(img, x, y) };
+                    let rgb = masks.map(|mask| channel_from_mask(value, mask));
+
+                    pixels.push(framebuffer::from_rgb(rgb.into()));
+                }
+            }
         }
 
         unsafe {
             xlib::XDestroyImage(img as *mut _);
         }
-        Some(image_buffer)
+        Some(Framebuffer::new(w, h, pixels))
     }
 }
 
-impl Drop for Screen {
+/// Extracts a channel from a pixel of a visual with arbitrary masks, scaled to 8 bits.
+const fn channel_from_mask(value: c_ulong, mask: c_ulong) -> u8 {
+    if mask == 0 {
+        return 0;
+    }
+
+    let max = mask >> mask.trailing_zeros();
+    let channel = (value & mask) >> mask.trailing_zeros();
+
+    (channel * 255 / max) as u8
+}
+
+const unsafe fn raw_slice<'a, T>(data: *const T, len: c_int) -> &'a [T] {
+    if data.is_null() || len <= 0 {
+        return &[];
+    }
+
+    slice::from_raw_parts(data, len as usize)
+}
+
+impl Drop for XDisplay {
     fn drop(&mut self) {
         unsafe {
             xlib::XCloseDisplay(self.display);
@@ -0,0 +1,249 @@
+use std::{
+    fs,
+    path::PathBuf,
+    thread::{self, JoinHandle},
+};
+
+use color_eyre::eyre::{eyre, Result};
+use serde_json::json;
+use winit::dpi::PhysicalPosition;
+
+use crate::args::{ColorFormat, TableFormat};
+use crate::framebuffer::{self, Framebuffer, Rect};
+use crate::hud;
+use crate::pin::{self, Pin};
+
+/// Space left around the pins when cropping, enough for their labels
+const CROP_MARGIN: i64 = 192;
+/// How far the label of a pin is moved away from it on either axis
+const LEADER_LENGTH: i64 = 48;
+
+/// Writes `desktop`, which starts at `origin`, to `path` as a PNG with every pin marked and
+/// labelled on it, and a table of the pins next to it with the extension of `table`.
+///
+/// Encoding a whole desktop takes a while, so the files are written on their own thread, which
+/// reports how it went once it is done.
+pub fn export(
+    path: PathBuf,
+    mut desktop: Framebuffer,
+    origin: PhysicalPosition<i32>,
+    pins: &[Pin],
+    format: ColorFormat,
+    table: TableFormat,
+) -> JoinHandle<()> {
+    annotate(&mut desktop, origin, pins, format);
+    let rendered = render_table(pins, table);
+
+    thread::spawn(move || {
+        let write = || -> Result<()> {
+            desktop
+                .to_image()
+                .save(&path)
+                .map_err(|err| eyre!("Could not save {}: {err}", path.display()))?;
+
+            let table_path = path.with_extension(table.extension());
+            fs::write(&table_path, rendered)
+                .map_err(|err| eyre!("Could not save {}: {err}", table_path.display()))
+        };
+
+        match write() {
+            Ok(()) => eprintln!("Exported the spec sheet to {}", path.display()),
+            Err(err) => eprintln!("Failed to export the spec sheet: {err}"),
+        }
+    })
+}
+
+/// The area around every pin of a `width` by `height` desktop starting at `origin`, `None`
+/// without any
+pub fn crop_area(
+    origin: PhysicalPosition<i32>,
+    width: u32,
+    height: u32,
+    pins: &[Pin],
+) -> Option<Rect> {
+    let xs = pins.iter().map(|pin| (pin.position.x - origin.x) as i64);
+    let ys = pins.iter().map(|pin| (pin.position.y - origin.y) as i64);
+
+    let left = (xs.clone().min()? - CROP_MARGIN).max(0);
+    let top = (ys.clone().min()? - CROP_MARGIN).max(0);
+    let right = (xs.max()? + CROP_MARGIN + 1).min(width.into());
+    let bottom = (ys.max()? + CROP_MARGIN + 1).min(height.into());
+
+    Some(Rect {
+        x: left as u32,
+        y: top as u32,
+        width: (right - left) as u32,
+        height: (bottom - top) as u32,
+    })
+}
+
+/// Marks every pin with a cross and a numbered label at the end of a line leading away from it,
+/// alternating between above and below so neighbouring labels do not cover each other
+fn annotate(
+    framebuffer: &mut Framebuffer,
+    origin: PhysicalPosition<i32>,
+    pins: &[Pin],
+    format: ColorFormat,
+) {
+    for (index, pin) in pins.iter().enumerate() {
+        let x = (pin.position.x - origin.x) as i64;
+        let y = (pin.position.y - origin.y) as i64;
+
+        let label_x = if x + LEADER_LENGTH < framebuffer.width() as i64 / 2 {
+            x + LEADER_LENGTH
+        } else {
+            x - LEADER_LENGTH
+        };
+        let label_y = if index % 2 == 0 {
+            y - LEADER_LENGTH
+        } else {
+            y + LEADER_LENGTH
+        };
+
+        framebuffer.draw_line((x, y), (label_x, label_y), pin::PIN_COLOR);
+        framebuffer.draw_cross(x, y, pin::PIN_COLOR);
+
+        let anchor = Rect {
+            x: label_x.clamp(0, framebuffer.width() as i64) as u32,
+            y: label_y.clamp(0, framebuffer.height() as i64) as u32,
+            width: 0,
+            height: 0,
+        };
+        let lines = [format!("{}: {}", index + 1, format.format(pin.pick.color))];
+
+        hud::draw_label(
+            framebuffer,
+            anchor,
+            &lines,
+            Some(framebuffer::from_rgb(pin.pick.color)),
+            pin::PIN_COLOR,
+        );
+    }
+}
+
+/// A table of the number, position and color of every pin
+fn render_table(pins: &[Pin], table: TableFormat) -> String {
+    let hex = |color| ColorFormat::Hex.format(color);
+
+    match table {
+        TableFormat::Markdown => {
+            let rows = pins
+                .iter()
+                .enumerate()
+                .map(|(index, pin)| {
+                    let (r, g, b) = pin.pick.color;
+
+                    format!(
+                        "| {} | {} | {} | {} | {r}, {g}, {b} |\n",
+                        index + 1,
+                        pin.position.x,
+                        pin.position.y,
+                        hex(pin.pick.color)
+                    )
+                })
+                .collect::<String>();
+
+            format!("| Pin | X | Y | Hex | RGB |\n| --- | --- | --- | --- | --- |\n{rows}")
+        }
+        TableFormat::Json => {
+            let rows = pins
+                .iter()
+                .enumerate()
+                .map(|(index, pin)| {
+                    let (r, g, b) = pin.pick.color;
+
+                    json!({
+                        "pin": index + 1,
+                        "x": pin.position.x,
+                        "y": pin.position.y,
+                        "hex": hex(pin.pick.color),
+                        "rgb": [r, g, b],
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            format!("{:#}\n", json!(rows))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::picker_context::Pick;
+
+    fn pin(x: i32, y: i32, color: (u8, u8, u8)) -> Pin {
+        Pin {
+            position: PhysicalPosition::new(x, y),
+            pick: Pick {
+                color,
+                simulated: None,
+            },
+            lens: None,
+        }
+    }
+
+    #[test]
+    fn crop_area_keeps_a_margin_inside_the_desktop() {
+        let origin = PhysicalPosition::new(-1920, 0);
+        let pins = [pin(-1900, 500, (0, 0, 0)), pin(100, 600, (0, 0, 0))];
+
+        assert_eq!(
+            crop_area(origin, 3840, 1080, &pins),
+            Some(Rect {
+                x: 0,
+                y: 500 - 192,
+                width: 1920 + 100 + 192 + 1,
+                height: 100 + 2 * 192 + 1,
+            })
+        );
+        assert_eq!(crop_area(origin, 3840, 1080, &[]), None);
+    }
+
+    #[test]
+    fn render_table_as_markdown() {
+        let pins = [pin(10, -20, (255, 128, 0)), pin(30, 40, (1, 2, 3))];
+
+        assert_eq!(
+            render_table(&pins, TableFormat::Markdown),
+            "| Pin | X | Y | Hex | RGB |\n\
+             | --- | --- | --- | --- | --- |\n\
+             | 1 | 10 | -20 | #FF8000 | 255, 128, 0 |\n\
+             | 2 | 30 | 40 | #010203 | 1, 2, 3 |\n"
+        );
+    }
+
+    #[test]
+    fn render_table_as_json() {
+        let pins = [pin(10, -20, (255, 128, 0))];
+        let table = render_table(&pins, TableFormat::Json);
+
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&table).unwrap(),
+            json!([{ "pin": 1, "x": 10, "y": -20, "hex": "#FF8000", "rgb": [255, 128, 0] }])
+        );
+        assert_eq!(render_table(&[], TableFormat::Json), "[]\n");
+    }
+
+    #[test]
+    fn annotate_marks_pins_and_shows_their_color() {
+        let (width, height) = (400, 300);
+        let mut framebuffer = Framebuffer::new(width, height, vec![0; (width * height) as usize]);
+        let color = (10, 200, 30);
+
+        annotate(
+            &mut framebuffer,
+            PhysicalPosition::new(100, 100),
+            &[pin(200, 250, color)],
+            ColorFormat::Hex,
+        );
+
+        let pixel = |x: u32, y: u32| framebuffer.pixels()[(y * width + x) as usize];
+
+        assert_eq!(pixel(100, 150), pin::PIN_COLOR);
+        assert_eq!(pixel(104, 150), pin::PIN_COLOR);
+        // The leader goes up and to the right of a pin left of the middle
+        assert_eq!(pixel(100 + 48, 150 - 48), pin::PIN_COLOR);
+        assert!(framebuffer.pixels().contains(&framebuffer::from_rgb(color)));
+    }
+}
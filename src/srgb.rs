@@ -0,0 +1,17 @@
+/// Converts an sRGB channel from 0 to 1 to linear light
+pub fn to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Converts linear light from 0 to 1 back to an sRGB channel
+pub fn from_linear(value: f32) -> f32 {
+    if value <= 0.0031308 {
+        value * 12.92
+    } else {
+        1.055f32.mul_add(value.powf(1.0 / 2.4), -0.055)
+    }
+}
@@ -0,0 +1,137 @@
+use std::collections::HashMap;
+
+use crate::framebuffer::{luma, to_rgb};
+
+/// Rounds of k-means to split the pixels into text and background
+const ITERATIONS: usize = 8;
+
+/// The colors of anti-aliased text around the cursor, estimated from its surroundings
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextColors {
+    pub foreground: (u8, u8, u8),
+    pub background: (u8, u8, u8),
+    /// From 0 to 1, how well the pixels fit being blends of only these two colors
+    pub confidence: f64,
+}
+
+type Color = [f64; 3];
+
+const fn to_color(pixel: u32) -> Color {
+    let (r, g, b) = to_rgb(pixel);
+    [r as f64, g as f64, b as f64]
+}
+
+fn distance(a: Color, b: Color) -> f64 {
+    (a[0] - b[0]).hypot(a[1] - b[1]).hypot(a[2] - b[2])
+}
+
+/// Splits `pixels` into two clusters starting from the darkest and the lightest one.
+///
+/// The background is the most common color of the bigger cluster, as it is usually flat, and the
+/// foreground is the pixel of the other cluster furthest from it, as every other text pixel is
+/// blended with the background. Returns `None` for no pixels.
+pub fn estimate(pixels: &[u32]) -> Option<TextColors> {
+    let darkest = *pixels.iter().min_by_key(|&&pixel| luma(pixel))?;
+    let lightest = *pixels.iter().max_by_key(|&&pixel| luma(pixel))?;
+
+    let colors = pixels
+        .iter()
+        .map(|&pixel| to_color(pixel))
+        .collect::<Vec<_>>();
+    let mut centers = [to_color(darkest), to_color(lightest)];
+    let mut in_second = vec![false; colors.len()];
+
+    for _ in 0..ITERATIONS {
+        for (color, second) in colors.iter().zip(&mut in_second) {
+            *second = distance(*color, centers[1]) < distance(*color, centers[0]);
+        }
+
+        for (index, center) in centers.iter_mut().enumerate() {
+            let members = colors
+                .iter()
+                .zip(&in_second)
+                .filter(|&(_, &second)| second == (index == 1))
+                .map(|(color, _)| color);
+
+            let (sum, count) = members.fold(([0.0; 3], 0), |(sum, count), color| {
+                (
+                    [sum[0] + color[0], sum[1] + color[1], sum[2] + color[2]],
+                    count + 1,
+                )
+            });
+
+            if count > 0 {
+                *center = sum.map(|channel| channel / count as f64);
+            }
+        }
+    }
+
+    let second_count = in_second.iter().filter(|&&second| second).count();
+    let background_is_second = second_count * 2 > colors.len();
+
+    let mut counts = HashMap::new();
+    for (&pixel, &second) in pixels.iter().zip(&in_second) {
+        if second == background_is_second {
+            *counts.entry(pixel).or_insert(0) += 1;
+        }
+    }
+
+    let background = counts
+        .into_iter()
+        .max_by_key(|&(pixel, count)| (count, pixel))
+        .map_or(darkest, |(pixel, _)| pixel);
+    let background_color = to_color(background);
+
+    let foreground = *pixels
+        .iter()
+        .zip(&in_second)
+        .filter(|&(_, &second)| second != background_is_second)
+        .map(|(pixel, _)| pixel)
+        .max_by(|&&a, &&b| {
+            distance(to_color(a), background_color)
+                .total_cmp(&distance(to_color(b), background_color))
+        })
+        .unwrap_or(&background);
+
+    Some(TextColors {
+        foreground: to_rgb(foreground),
+        background: to_rgb(background),
+        confidence: confidence(&colors, background_color, to_color(foreground)),
+    })
+}
+
+/// How close the pixels are to the line between the two colors, scaled down when the colors are
+/// too alike to tell text apart at all
+fn confidence(colors: &[Color], background: Color, foreground: Color) -> f64 {
+    let length = distance(background, foreground);
+
+    if length < 1.0 {
+        return 0.0;
+    }
+
+    let direction = [0, 1, 2].map(|channel| (foreground[channel] - background[channel]) / length);
+
+    let off_line = colors
+        .iter()
+        .map(|color| {
+            let offset = [0, 1, 2].map(|channel| color[channel] - background[channel]);
+            let along = offset[2]
+                .mul_add(
+                    direction[2],
+                    offset[1].mul_add(direction[1], offset[0] * direction[0]),
+                )
+                .clamp(0.0, length);
+            let closest =
+                [0, 1, 2].map(|channel| direction[channel].mul_add(along, background[channel]));
+
+            distance(*color, closest)
+        })
+        .sum::<f64>()
+        / colors.len() as f64;
+
+    // Half the distance between the colors away from the line on average is as bad as noise
+    let fit = 2.0f64.mul_add(-off_line / length, 1.0).max(0.0);
+    let separation = (length / 128.0).min(1.0);
+
+    fit * separation
+}
@@ -0,0 +1,63 @@
+use std::{
+    fmt::Display,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+/// Collects how long each stage of starting the picker took, for `--timings`.
+///
+/// Stages can be recorded from the capture threads, they are reported in the order they finished.
+pub struct Timings {
+    enabled: bool,
+    start: Instant,
+    stages: Mutex<Vec<(String, Duration)>>,
+    reported: AtomicBool,
+}
+
+impl Timings {
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            enabled,
+            start: Instant::now(),
+            stages: Mutex::new(Vec::new()),
+            reported: AtomicBool::new(false),
+        }
+    }
+
+    /// Runs `stage` and records how long it took
+    pub fn time<T>(&self, name: impl Display, stage: impl FnOnce() -> T) -> T {
+        if !self.enabled {
+            return stage();
+        }
+
+        let start = Instant::now();
+        let result = stage();
+        self.record(name, start.elapsed());
+
+        result
+    }
+
+    fn record(&self, name: impl Display, duration: Duration) {
+        self.stages
+            .lock()
+            .unwrap()
+            .push((name.to_string(), duration));
+    }
+
+    /// Records the time since startup as the final stage and prints every stage, only once
+    pub fn finish(&self, name: impl Display) {
+        if !self.enabled || self.reported.swap(true, Ordering::Relaxed) {
+            return;
+        }
+
+        self.record(name, self.start.elapsed());
+
+        eprintln!("Startup timings:");
+        for (name, duration) in self.stages.lock().unwrap().iter() {
+            eprintln!("  {name:<32}{:>10.2}ms", duration.as_secs_f64() * 1000.0);
+        }
+    }
+}
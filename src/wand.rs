@@ -0,0 +1,245 @@
+use std::fmt::{self, Display};
+
+use winit::dpi::PhysicalPosition;
+
+use crate::framebuffer::{to_rgb, Framebuffer, Rect};
+use crate::measure;
+
+/// The overlay color of the outline of a filled region
+pub const REGION_COLOR: u32 = 0xFFFF00;
+
+/// The pixels connected to a clicked one that are close enough to its color
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Region {
+    /// Where the bounding box starts in global desktop coordinates
+    pub position: PhysicalPosition<i32>,
+    pub width: u32,
+    pub height: u32,
+    /// How many pixels were filled
+    pub pixels: usize,
+    /// The mean color of the filled pixels
+    pub color: (u8, u8, u8),
+    /// Whether each pixel of the bounding box was filled, row by row
+    pub mask: Vec<bool>,
+}
+
+impl Region {
+    /// Whether a global desktop position was filled
+    pub fn contains(&self, position: PhysicalPosition<i32>) -> bool {
+        let x = u32::try_from(position.x - self.position.x).ok();
+        let y = u32::try_from(position.y - self.position.y).ok();
+
+        x.zip(y)
+            .filter(|&(x, y)| x < self.width && y < self.height)
+            .is_some_and(|(x, y)| self.mask[(y * self.width + x) as usize])
+    }
+
+    /// The lines shown next to the cursor while a region is filled
+    pub fn lines(&self) -> [String; 2] {
+        [
+            format!(
+                "box: {}x{} at {}, {}",
+                self.width, self.height, self.position.x, self.position.y
+            ),
+            format!("pixels: {}", self.pixels),
+        ]
+    }
+}
+
+impl Display for Region {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "x: {} y: {} width: {} height: {} pixels: {}",
+            self.position.x, self.position.y, self.width, self.height, self.pixels
+        )
+    }
+}
+
+/// Fills the capture of one monitor outwards from `start` through every pixel whose channels are
+/// all within `tolerance` of its color, `origin` being where the monitor starts.
+///
+/// Pixels only connect through their sides, so a fill does not leak through diagonal gaps.
+pub fn flood_fill(
+    framebuffer: &Framebuffer,
+    origin: PhysicalPosition<i32>,
+    start: PhysicalPosition<u32>,
+    tolerance: u8,
+) -> Option<Region> {
+    let (width, height) = (framebuffer.width(), framebuffer.height());
+
+    if start.x >= width || start.y >= height {
+        return None;
+    }
+
+    // Every pixel is read a few times, which is much cheaper on a plain copy of the capture than
+    // looking past everything drawn over it each time
+    let capture = framebuffer.capture_crop(Rect {
+        x: 0,
+        y: 0,
+        width,
+        height,
+    });
+    let capture = capture.pixels();
+    let pixel = |x: u32, y: u32| capture[(y * width + x) as usize];
+
+    let target = pixel(start.x, start.y);
+
+    let mut filled = vec![false; width as usize * height as usize];
+    let mut stack = vec![(start.x, start.y)];
+    filled[(start.y * width + start.x) as usize] = true;
+
+    let (mut left, mut top, mut right, mut bottom) = (start.x, start.y, start.x, start.y);
+    let mut sums = [0u64; 3];
+    let mut pixels = 0;
+
+    while let Some((x, y)) = stack.pop() {
+        let (r, g, b) = to_rgb(pixel(x, y));
+        sums[0] += u64::from(r);
+        sums[1] += u64::from(g);
+        sums[2] += u64::from(b);
+        pixels += 1;
+
+        (left, top, right, bottom) = (left.min(x), top.min(y), right.max(x), bottom.max(y));
+
+        let neighbours = [
+            x.checked_sub(1).map(|x| (x, y)),
+            (x + 1 < width).then_some((x + 1, y)),
+            y.checked_sub(1).map(|y| (x, y)),
+            (y + 1 < height).then_some((x, y + 1)),
+        ];
+
+        for (x, y) in neighbours.into_iter().flatten() {
+            let index = (y * width + x) as usize;
+
+            if !filled[index] && measure::color_difference(target, pixel(x, y)) <= tolerance {
+                filled[index] = true;
+                stack.push((x, y));
+            }
+        }
+    }
+
+    let (box_width, box_height) = (right - left + 1, bottom - top + 1);
+    let mask = (top..=bottom)
+        .flat_map(|y| (left..=right).map(move |x| (x, y)))
+        .map(|(x, y)| filled[(y * width + x) as usize])
+        .collect();
+
+    let mean = |sum: u64| (sum as f64 / pixels as f64).round() as u8;
+
+    Some(Region {
+        position: PhysicalPosition::new(origin.x + left as i32, origin.y + top as i32),
+        width: box_width,
+        height: box_height,
+        pixels,
+        color: (mean(sums[0]), mean(sums[1]), mean(sums[2])),
+        mask,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A framebuffer from rows of text, `#` being black and anything else white
+    fn framebuffer(rows: &[&str]) -> Framebuffer {
+        let pixels = rows
+            .iter()
+            .flat_map(|row| row.chars())
+            .map(|pixel| if pixel == '#' { 0x000000 } else { 0xFFFFFF })
+            .collect();
+
+        Framebuffer::new(rows[0].len() as u32, rows.len() as u32, pixels)
+    }
+
+    #[test]
+    fn fill_does_not_leak_through_diagonal_gaps() {
+        #[rustfmt::skip]
+        let framebuffer = framebuffer(&[
+            "..#..",
+            ".#...",
+            "#....",
+        ]);
+
+        let region = flood_fill(
+            &framebuffer,
+            PhysicalPosition::new(0, 0),
+            PhysicalPosition::new(0, 0),
+            0,
+        )
+        .unwrap();
+
+        assert_eq!(region.pixels, 3);
+        assert_eq!((region.width, region.height), (2, 2));
+        assert!(!region.contains(PhysicalPosition::new(1, 1)));
+        assert!(!region.contains(PhysicalPosition::new(3, 0)));
+    }
+
+    #[test]
+    fn fill_reports_its_bounds_in_desktop_coordinates() {
+        #[rustfmt::skip]
+        let framebuffer = framebuffer(&[
+            ".....",
+            ".##..",
+            "..##.",
+            ".....",
+        ]);
+
+        let region = flood_fill(
+            &framebuffer,
+            PhysicalPosition::new(-1920, 100),
+            PhysicalPosition::new(2, 1),
+            0,
+        )
+        .unwrap();
+
+        assert_eq!(region.position, PhysicalPosition::new(-1919, 101));
+        assert_eq!((region.width, region.height), (3, 2));
+        assert_eq!(region.pixels, 4);
+        assert_eq!(region.mask, [true, true, false, false, true, true]);
+        assert_eq!(region.color, (0, 0, 0));
+    }
+
+    #[test]
+    fn fill_takes_colors_within_tolerance() {
+        let pixels = vec![0x101010, 0x141414, 0x181818, 0x202020];
+        let framebuffer = Framebuffer::new(4, 1, pixels);
+        let fill = |tolerance| {
+            flood_fill(
+                &framebuffer,
+                PhysicalPosition::new(0, 0),
+                PhysicalPosition::new(0, 0),
+                tolerance,
+            )
+            .unwrap()
+        };
+
+        // Every pixel is compared against the clicked one, not the one it was reached from
+        assert_eq!(fill(4).pixels, 2);
+        assert_eq!(fill(8).pixels, 3);
+        assert_eq!(fill(16).pixels, 4);
+        assert_eq!(fill(8).color, (0x14, 0x14, 0x14));
+    }
+
+    #[test]
+    fn fill_ignores_overlays_and_clicks_outside() {
+        let mut framebuffer = framebuffer(&["...", "...", "..."]);
+        framebuffer.fill_rect(1, 0, 1, 3, 0x000000);
+
+        let region = flood_fill(
+            &framebuffer,
+            PhysicalPosition::new(0, 0),
+            PhysicalPosition::new(0, 0),
+            0,
+        );
+
+        assert_eq!(region.map(|region| region.pixels), Some(9));
+        assert!(flood_fill(
+            &framebuffer,
+            PhysicalPosition::new(0, 0),
+            PhysicalPosition::new(3, 0),
+            0
+        )
+        .is_none());
+    }
+}